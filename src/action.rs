@@ -0,0 +1,219 @@
+//! Window management actions.
+//!
+//! Actions are what key bindings trigger. They are parsed from strings such as
+//! `"close"` or `"move -10 0"` and executed by `WindowManager::dispatch`.
+
+use std::str::FromStr;
+use crate::spawn;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Ask the window to close, or kill it if it does not support WM_DELETE_WINDOW.
+    Close,
+    /// Kill the client owning the window.
+    Kill,
+    FocusNext,
+    FocusPrev,
+    /// Focus the closest window in the given direction.
+    FocusDirection(Direction),
+    /// Exchange the places of the window and of the closest one in the given
+    /// direction.
+    SwapDirection(Direction),
+    Raise,
+    Lower,
+    /// Move the window by the given offset.
+    Move(i32, i32),
+    /// Grow the window by the given amounts.
+    Resize(i32, i32),
+    /// Read the configuration file again.
+    Reload,
+    /// Run a program, given as the words of its command line.
+    Spawn(Vec<String>),
+    /// Show the workspace with the given index.
+    SwitchWorkspace(usize),
+    /// Send the window to the workspace with the given index.
+    MoveToWorkspace(usize),
+    /// Show the previously visited workspace.
+    LastWorkspace,
+    /// Switch the current workspace between tiling and floating.
+    ToggleTiling,
+    /// Change the number of clients in the master area by the given amount.
+    IncMaster(i32),
+    /// Change the part of the screen used by the master area by the given amount.
+    AdjustMasterRatio(f32),
+    /// Tile the current workspace with the next available layout.
+    CycleLayout,
+    /// Tile the current workspace with the named layout.
+    SetLayout(String),
+    /// Make the window cover the whole screen, or restore it.
+    ToggleFullscreen,
+    /// Take the window out of the layout of its workspace, or put it back.
+    ToggleFloating,
+    /// Focus the monitor that many monitors after (or before, if negative) the
+    /// current one.
+    FocusMonitor(i32),
+    /// Send the window to the monitor that many monitors after (or before)
+    /// its own.
+    MoveToMonitor(i32),
+    /// Turn presentation mode on or off.
+    TogglePresentation,
+    /// Add the window to, or remove it from, the windows shown while presenting.
+    ToggleShared,
+}
+
+/// Direction on the screen, for actions looking for a neighboring window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Action, String> {
+        // The command line of spawn is split like a shell would do it.
+        if let Some(command) = s.trim_start().strip_prefix("spawn ") {
+            let argv = spawn::split_words(command)?;
+            if argv.is_empty() {
+                return Err(format!("missing command in '{}'", s));
+            }
+            return Ok(Action::Spawn(argv));
+        }
+        let mut words = s.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let args: Vec<&str> = words.collect();
+        let action = match name {
+            "close" => Action::Close,
+            "kill" => Action::Kill,
+            "focus_next" => Action::FocusNext,
+            "focus_prev" => Action::FocusPrev,
+            "focus" => return Ok(Action::FocusDirection(parse_direction(s, &args)?)),
+            "swap" => return Ok(Action::SwapDirection(parse_direction(s, &args)?)),
+            "raise" => Action::Raise,
+            "lower" => Action::Lower,
+            "move" => {
+                let (dx, dy) = parse_pair(s, &args)?;
+                return Ok(Action::Move(dx, dy));
+            }
+            "resize" => {
+                let (dw, dh) = parse_pair(s, &args)?;
+                return Ok(Action::Resize(dw, dh));
+            }
+            "reload" => Action::Reload,
+            // Workspaces are numbered from 1 in the configuration.
+            "workspace" => return Ok(Action::SwitchWorkspace(parse_workspace(s, &args)?)),
+            "move_to_workspace" => return Ok(Action::MoveToWorkspace(parse_workspace(s, &args)?)),
+            "last_workspace" => Action::LastWorkspace,
+            "toggle_tiling" => Action::ToggleTiling,
+            "toggle_floating" => Action::ToggleFloating,
+            "toggle_fullscreen" => Action::ToggleFullscreen,
+            "cycle_layout" => Action::CycleLayout,
+            "toggle_presentation" => Action::TogglePresentation,
+            "toggle_shared" => Action::ToggleShared,
+            "layout" => match args.as_slice() {
+                [name] => return Ok(Action::SetLayout(name.to_string())),
+                _ => return Err(format!("invalid action '{}', expected a layout name", s)),
+            },
+            "inc_master" => match args.as_slice() {
+                [n] => return n.parse().map(Action::IncMaster)
+                    .map_err(|_| format!("invalid action '{}', expected an integer", s)),
+                _ => return Err(format!("invalid action '{}', expected an integer", s)),
+            },
+            "focus_monitor" => match args.as_slice() {
+                [n] => return n.parse().map(Action::FocusMonitor)
+                    .map_err(|_| format!("invalid action '{}', expected an integer", s)),
+                _ => return Err(format!("invalid action '{}', expected an integer", s)),
+            },
+            "move_to_monitor" => match args.as_slice() {
+                [n] => return n.parse().map(Action::MoveToMonitor)
+                    .map_err(|_| format!("invalid action '{}', expected an integer", s)),
+                _ => return Err(format!("invalid action '{}', expected an integer", s)),
+            },
+            "master_ratio" => match args.as_slice() {
+                [delta] => return delta.parse().map(Action::AdjustMasterRatio)
+                    .map_err(|_| format!("invalid action '{}', expected a number", s)),
+                _ => return Err(format!("invalid action '{}', expected a number", s)),
+            },
+            _ => return Err(format!("unknown action '{}'", s)),
+        };
+        if !args.is_empty() {
+            return Err(format!("action '{}' takes no argument", name));
+        }
+        Ok(action)
+    }
+}
+
+fn parse_workspace(s: &str, args: &[&str]) -> Result<usize, String> {
+    match args {
+        [n] => match n.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n - 1),
+            _ => Err(format!("invalid action '{}', expected a workspace number", s)),
+        },
+        _ => Err(format!("invalid action '{}', expected a workspace number", s)),
+    }
+}
+
+fn parse_direction(s: &str, args: &[&str]) -> Result<Direction, String> {
+    match args {
+        ["left"] => Ok(Direction::Left),
+        ["right"] => Ok(Direction::Right),
+        ["up"] => Ok(Direction::Up),
+        ["down"] => Ok(Direction::Down),
+        _ => Err(format!("invalid action '{}', expected left, right, up or down", s)),
+    }
+}
+
+fn parse_pair(s: &str, args: &[&str]) -> Result<(i32, i32), String> {
+    let invalid = || format!("invalid action '{}', expected two integers", s);
+    match args {
+        [a, b] => Ok((a.parse().map_err(|_| invalid())?, b.parse().map_err(|_| invalid())?)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_actions_with_arguments() {
+        assert_eq!("close".parse(), Ok(Action::Close));
+        assert_eq!("move -10 5".parse(), Ok(Action::Move(-10, 5)));
+        assert_eq!("workspace 1".parse(), Ok(Action::SwitchWorkspace(0)));
+        assert_eq!("move_to_workspace 9".parse(), Ok(Action::MoveToWorkspace(8)));
+        assert_eq!("master_ratio -0.05".parse(), Ok(Action::AdjustMasterRatio(-0.05)));
+        assert_eq!("focus left".parse(), Ok(Action::FocusDirection(Direction::Left)));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!("close now".parse::<Action>().is_err());
+        assert!("frobnicate".parse::<Action>().is_err());
+        assert!("move 10".parse::<Action>().is_err());
+        assert!("move 10 x".parse::<Action>().is_err());
+        assert!("inc_master".parse::<Action>().is_err());
+        assert!("focus sideways".parse::<Action>().is_err());
+        assert!("layout".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn rejects_workspace_zero() {
+        assert!("workspace 0".parse::<Action>().is_err());
+        assert!("move_to_workspace 0".parse::<Action>().is_err());
+        assert!("workspace -1".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn splits_spawn_command_lines() {
+        let argv = |words: &[&str]| Ok(Action::Spawn(words.iter().map(|w| w.to_string()).collect()));
+        assert_eq!("spawn xterm".parse(), argv(&["xterm"]));
+        assert_eq!("spawn xterm -e 'top -d 1'".parse(), argv(&["xterm", "-e", "top -d 1"]));
+        assert_eq!(r#"spawn sh -c "echo \"hi\"""#.parse(), argv(&["sh", "-c", "echo \"hi\""]));
+        assert_eq!(r"spawn echo a\ b".parse(), argv(&["echo", "a b"]));
+        assert!("spawn xterm 'oops".parse::<Action>().is_err());
+        assert!("spawn ".parse::<Action>().is_err());
+    }
+}
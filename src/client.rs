@@ -0,0 +1,258 @@
+//! Managed windows.
+
+use std::collections::HashMap;
+use x11::xlib;
+use crate::hints::SizeHints;
+use crate::layout::Rect;
+
+/// A top-level window managed by the window manager, and its frame.
+pub struct Client {
+    pub window: xlib::Window,
+    pub frame: xlib::Window,
+    /// Position of the frame and size of the client window, which is also the
+    /// size of the frame without its border.
+    pub geometry: Rect,
+    pub title: String,
+    /// Instance and class names, from WM_CLASS.
+    pub instance: String,
+    pub class: String,
+    pub size_hints: SizeHints,
+    /// Whether the window manager gives the input focus to the client, from
+    /// WM_HINTS.
+    pub accepts_input: bool,
+    /// Client window this one is a dialog of, from WM_TRANSIENT_FOR.
+    pub transient_for: Option<xlib::Window>,
+    /// Whether the client is left out of the layout of its workspace.
+    pub floating: bool,
+    /// Workspace of the client. Sticky clients follow the current workspace.
+    pub workspace: usize,
+    /// Index of the monitor showing the client.
+    pub monitor: usize,
+    /// States from _NET_WM_STATE.
+    pub fullscreen: bool,
+    pub maximized_vert: bool,
+    pub maximized_horz: bool,
+    pub above: bool,
+    pub below: bool,
+    pub sticky: bool,
+    pub hidden: bool,
+    pub urgent: bool,
+    /// Geometry to restore when the client leaves fullscreen or maximized
+    /// state.
+    pub saved_geometry: Option<Rect>,
+}
+
+impl Client {
+    pub fn new(window: xlib::Window, frame: xlib::Window, geometry: Rect, workspace: usize, monitor: usize) -> Client {
+        Client {
+            window,
+            frame,
+            geometry,
+            title: String::new(),
+            instance: String::new(),
+            class: String::new(),
+            size_hints: SizeHints::default(),
+            accepts_input: true,
+            transient_for: None,
+            floating: false,
+            workspace,
+            monitor,
+            fullscreen: false,
+            maximized_vert: false,
+            maximized_horz: false,
+            above: false,
+            below: false,
+            sticky: false,
+            hidden: false,
+            urgent: false,
+            saved_geometry: None,
+        }
+    }
+
+    /// Whether the client is placed by the layout of its workspace, if tiled.
+    pub fn is_tiled(&self) -> bool {
+        !(self.floating || self.fullscreen || self.maximized_vert || self.maximized_horz || self.hidden)
+    }
+
+    /// Stacking layer of the client: below, normal or above other clients.
+    pub fn layer(&self) -> u8 {
+        if self.below {
+            0
+        } else if self.above || self.fullscreen {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// Every managed client, indexed by client window and by frame.
+#[derive(Default)]
+pub struct ClientModel {
+    clients: HashMap<xlib::Window, Client>,
+    /// Client window of each frame.
+    frames: HashMap<xlib::Window, xlib::Window>,
+    /// Client windows, in the order they were managed, unless swapped. This
+    /// is the order in which layouts place them.
+    order: Vec<xlib::Window>,
+    /// Client windows, from bottom to top of the stack.
+    stack: Vec<xlib::Window>,
+}
+
+impl ClientModel {
+    pub fn insert(&mut self, client: Client) {
+        self.frames.insert(client.frame, client.window);
+        self.order.push(client.window);
+        self.stack.push(client.window);
+        self.clients.insert(client.window, client);
+    }
+
+    pub fn remove(&mut self, w: xlib::Window) -> Option<Client> {
+        let client = self.clients.remove(&w)?;
+        self.frames.remove(&client.frame);
+        for transient in self.clients.values_mut().filter(|c| c.transient_for == Some(w)) {
+            transient.transient_for = None;
+        }
+        self.order.retain(|&x| x != w);
+        self.stack.retain(|&x| x != w);
+        Some(client)
+    }
+
+    pub fn contains(&self, w: xlib::Window) -> bool {
+        self.clients.contains_key(&w)
+    }
+
+    pub fn get(&self, w: xlib::Window) -> Option<&Client> {
+        self.clients.get(&w)
+    }
+
+    pub fn get_mut(&mut self, w: xlib::Window) -> Option<&mut Client> {
+        self.clients.get_mut(&w)
+    }
+
+    /// The client window of `w`, which is either a client window or a frame.
+    pub fn resolve(&self, w: xlib::Window) -> Option<xlib::Window> {
+        if self.clients.contains_key(&w) {
+            Some(w)
+        } else {
+            self.frames.get(&w).copied()
+        }
+    }
+
+    /// Record that client window `w` was raised to the top of its layer.
+    pub fn raise(&mut self, w: xlib::Window) {
+        self.stack.retain(|&x| x != w);
+        self.stack.push(w);
+        self.sort_stack();
+    }
+
+    /// Record that client window `w` was lowered to the bottom of its layer.
+    pub fn lower(&mut self, w: xlib::Window) {
+        self.stack.retain(|&x| x != w);
+        self.stack.insert(0, w);
+        self.sort_stack();
+    }
+
+    /// Keep every layer above the lower ones, preserving the order within
+    /// each layer, and transients right above their parent.
+    pub fn sort_stack(&mut self) {
+        let clients = &self.clients;
+        self.stack.sort_by_key(|w| clients[w].layer());
+        let mut i = 0;
+        while i < self.stack.len() {
+            let w = self.stack[i];
+            let parent = self.clients[&w].transient_for
+                .and_then(|parent| self.stack.iter().position(|&x| x == parent));
+            match parent {
+                Some(p) if p > i => {
+                    self.stack.remove(i);
+                    self.stack.insert(p, w);
+                },
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// Exchange the places of `a` and `b` in the order of the clients.
+    pub fn swap(&mut self, a: xlib::Window, b: xlib::Window) {
+        let i = self.order.iter().position(|&x| x == a);
+        let j = self.order.iter().position(|&x| x == b);
+        if let (Some(i), Some(j)) = (i, j) {
+            self.order.swap(i, j);
+        }
+    }
+
+    /// Client windows that are transients of `w`.
+    pub fn transients_of(&self, w: xlib::Window) -> Vec<xlib::Window> {
+        self.iter().filter(|c| c.transient_for == Some(w)).map(|c| c.window).collect()
+    }
+
+    /// Client windows, in the order they were managed, unless swapped.
+    pub fn windows(&self) -> &[xlib::Window] {
+        &self.order
+    }
+
+    /// Client windows, from bottom to top of the stack.
+    pub fn stacking(&self) -> &[xlib::Window] {
+        &self.stack
+    }
+
+    /// Every client, in the order they were managed, unless swapped.
+    pub fn iter(&self) -> impl Iterator<Item = &Client> {
+        self.order.iter().map(move |w| &self.clients[w])
+    }
+
+    /// Every client, in no particular order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Client> {
+        self.clients.values_mut()
+    }
+
+    /// Clients on workspace `n`, in the order they were managed, unless swapped.
+    pub fn on_workspace(&self, n: usize) -> impl Iterator<Item = &Client> {
+        self.iter().filter(move |client| client.workspace == n)
+    }
+}
+
+impl std::ops::Index<xlib::Window> for ClientModel {
+    type Output = Client;
+
+    fn index(&self, w: xlib::Window) -> &Client {
+        &self.clients[&w]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(clients: Vec<Client>) -> ClientModel {
+        let mut model = ClientModel::default();
+        for client in clients {
+            model.insert(client);
+        }
+        model
+    }
+
+    fn client(w: xlib::Window) -> Client {
+        Client::new(w, w + 100, Rect::default(), 0, 0)
+    }
+
+    fn transient(w: xlib::Window, parent: xlib::Window) -> Client {
+        Client { transient_for: Some(parent), ..client(w) }
+    }
+
+    #[test]
+    fn transient_follows_its_fullscreen_parent() {
+        let mut clients = model(vec![client(1), transient(2, 3), client(3), client(4)]);
+        clients.get_mut(3).unwrap().fullscreen = true;
+        clients.sort_stack();
+        assert_eq!(clients.stacking(), &[1, 4, 3, 2]);
+    }
+
+    #[test]
+    fn transient_above_its_parent_stays_put() {
+        let mut clients = model(vec![client(1), client(2), transient(3, 1)]);
+        clients.sort_stack();
+        assert_eq!(clients.stacking(), &[1, 2, 3]);
+    }
+}
@@ -0,0 +1,521 @@
+//! User configuration.
+//!
+//! The configuration is read from `$XDG_CONFIG_HOME/rswm/config.toml` (or
+//! `$HOME/.config/rswm/config.toml`). Every setting is optional, a missing file
+//! yields the built-in defaults:
+//!
+//! ```toml
+//! border_width = 3
+//! border_color = "#ff0000"
+//! urgent_color = "#ffff00"  # border of windows demanding attention
+//! bg_color = "#0000ff"
+//! modifier = "Control"
+//! workspaces = ["1", "2", "3", "4", "5", "6", "7", "8", "9"]
+//! workspace_mode = "global"  # or "per_monitor": each monitor shows its own
+//! tiling = false        # whether workspaces start in tiling mode
+//! layout = "tile"       # "tile", "bstack", "monocle" or "grid"
+//! master_count = 1
+//! master_ratio = 0.55
+//! presentation_classes = []  # WM_CLASS names turning presentation mode on
+//! focus_model = "click"  # "click", "follow_mouse" or "sloppy"
+//! raise_on_focus = true  # whether windows focused with the mouse are raised
+//!
+//! [keys]
+//! "Mod+F4" = "close"
+//! "Mod+Tab" = "focus_next"           # cycle while Mod is held
+//! "Mod+Shift+Tab" = "focus_prev"
+//! "Mod+Left" = "focus left"        # also right, up and down
+//! "Mod+Shift+Left" = "swap left"   # also right, up and down
+//! "Mod+Shift+r" = "reload"
+//! "Mod+Return" = "spawn xterm"
+//! "Mod+1" = "workspace 1"                # ... up to Mod+9
+//! "Mod+Shift+1" = "move_to_workspace 1"  # ... up to Mod+Shift+9
+//! "Mod+grave" = "last_workspace"
+//! "Mod+Shift+t" = "toggle_tiling"
+//! "Mod+Shift+i" = "inc_master 1"
+//! "Mod+Shift+d" = "inc_master -1"
+//! "Mod+Shift+h" = "master_ratio -0.05"
+//! "Mod+Shift+l" = "master_ratio 0.05"
+//! "Mod+space" = "cycle_layout"
+//! "Mod+Shift+space" = "toggle_floating"
+//! "Mod+Shift+f" = "toggle_fullscreen"
+//! "Mod+Shift+p" = "toggle_presentation"
+//! "Mod+Shift+s" = "toggle_shared"
+//! "Mod+period" = "focus_monitor 1"
+//! "Mod+comma" = "focus_monitor -1"
+//! "Mod+Shift+period" = "move_to_monitor 1"
+//! "Mod+Shift+comma" = "move_to_monitor -1"
+//!
+//! [buttons]
+//! "Mod+Button1" = "move"
+//! "Mod+Button3" = "resize"
+//! ```
+//!
+//! `Mod` in a binding stands for the configured `modifier`. Key actions are
+//! described in the `action` module, for instance `"move -10 0"`. A `[keys]` or
+//! `[buttons]` table replaces the corresponding default table entirely.
+//!
+//! The file is read again by the `reload` action and on SIGHUP.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use x11::xlib;
+use crate::action::Action;
+use crate::layout::{self, LayoutParams};
+
+static DEFAULT_KEYS: [(&str, &str); 46] = [
+    ("Mod+F4", "close"),
+    ("Mod+Tab", "focus_next"),
+    ("Mod+Shift+Tab", "focus_prev"),
+    ("Mod+Left", "focus left"),
+    ("Mod+Right", "focus right"),
+    ("Mod+Up", "focus up"),
+    ("Mod+Down", "focus down"),
+    ("Mod+Shift+Left", "swap left"),
+    ("Mod+Shift+Right", "swap right"),
+    ("Mod+Shift+Up", "swap up"),
+    ("Mod+Shift+Down", "swap down"),
+    ("Mod+Shift+r", "reload"),
+    ("Mod+Return", "spawn xterm"),
+    ("Mod+1", "workspace 1"),
+    ("Mod+2", "workspace 2"),
+    ("Mod+3", "workspace 3"),
+    ("Mod+4", "workspace 4"),
+    ("Mod+5", "workspace 5"),
+    ("Mod+6", "workspace 6"),
+    ("Mod+7", "workspace 7"),
+    ("Mod+8", "workspace 8"),
+    ("Mod+9", "workspace 9"),
+    ("Mod+Shift+1", "move_to_workspace 1"),
+    ("Mod+Shift+2", "move_to_workspace 2"),
+    ("Mod+Shift+3", "move_to_workspace 3"),
+    ("Mod+Shift+4", "move_to_workspace 4"),
+    ("Mod+Shift+5", "move_to_workspace 5"),
+    ("Mod+Shift+6", "move_to_workspace 6"),
+    ("Mod+Shift+7", "move_to_workspace 7"),
+    ("Mod+Shift+8", "move_to_workspace 8"),
+    ("Mod+Shift+9", "move_to_workspace 9"),
+    ("Mod+grave", "last_workspace"),
+    ("Mod+Shift+t", "toggle_tiling"),
+    ("Mod+Shift+i", "inc_master 1"),
+    ("Mod+Shift+d", "inc_master -1"),
+    ("Mod+Shift+h", "master_ratio -0.05"),
+    ("Mod+Shift+l", "master_ratio 0.05"),
+    ("Mod+space", "cycle_layout"),
+    ("Mod+Shift+space", "toggle_floating"),
+    ("Mod+Shift+f", "toggle_fullscreen"),
+    ("Mod+Shift+p", "toggle_presentation"),
+    ("Mod+Shift+s", "toggle_shared"),
+    ("Mod+period", "focus_monitor 1"),
+    ("Mod+comma", "focus_monitor -1"),
+    ("Mod+Shift+period", "move_to_monitor 1"),
+    ("Mod+Shift+comma", "move_to_monitor -1"),
+];
+
+static DEFAULT_WORKSPACES: [&str; 9] = ["1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+static DEFAULT_BUTTONS: [(&str, &str); 2] = [
+    ("Mod+Button1", "move"),
+    ("Mod+Button3", "resize"),
+];
+
+/// Modifiers that are meaningful when matching a binding against an event.
+pub const MODIFIER_MASK: u32 = xlib::ShiftMask
+    | xlib::LockMask
+    | xlib::ControlMask
+    | xlib::Mod1Mask
+    | xlib::Mod2Mask
+    | xlib::Mod3Mask
+    | xlib::Mod4Mask
+    | xlib::Mod5Mask;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "cannot read {}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => write!(f, "cannot parse {}: {}", path.display(), err),
+            ConfigError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// How workspaces are shown on multiple monitors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorkspaceMode {
+    /// Every monitor shows the current workspace, like a single large
+    /// desktop.
+    Global,
+    /// Every monitor shows a workspace of its own. Selecting the workspace of
+    /// another monitor swaps the workspaces of both monitors.
+    PerMonitor,
+}
+
+impl FromStr for WorkspaceMode {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<WorkspaceMode, ConfigError> {
+        match s {
+            "global" => Ok(WorkspaceMode::Global),
+            "per_monitor" => Ok(WorkspaceMode::PerMonitor),
+            _ => Err(ConfigError::Invalid(format!("unknown workspace mode '{}'", s))),
+        }
+    }
+}
+
+/// How the pointer moves the focus. A click on a window always focuses it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FocusModel {
+    /// Only clicks move the focus.
+    Click,
+    /// The window under the pointer has the focus, and none has it when the
+    /// pointer is over the desktop.
+    FollowMouse,
+    /// Like `FollowMouse`, but the focus stays on the last window when the
+    /// pointer moves to the desktop.
+    Sloppy,
+}
+
+impl FromStr for FocusModel {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<FocusModel, ConfigError> {
+        match s {
+            "click" => Ok(FocusModel::Click),
+            "follow_mouse" => Ok(FocusModel::FollowMouse),
+            "sloppy" => Ok(FocusModel::Sloppy),
+            _ => Err(ConfigError::Invalid(format!("unknown focus model '{}'", s))),
+        }
+    }
+}
+
+/// Action triggered by a mouse binding. It lasts until the button is released.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ButtonAction {
+    Move,
+    Resize,
+}
+
+impl FromStr for ButtonAction {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<ButtonAction, ConfigError> {
+        match s {
+            "move" => Ok(ButtonAction::Move),
+            "resize" => Ok(ButtonAction::Resize),
+            _ => Err(ConfigError::Invalid(format!("unknown button action '{}'", s))),
+        }
+    }
+}
+
+pub struct KeyBinding {
+    pub modifiers: u32,
+    pub keysym: xlib::KeySym,
+    pub action: Action,
+}
+
+pub struct ButtonBinding {
+    pub modifiers: u32,
+    pub button: u32,
+    pub action: ButtonAction,
+}
+
+pub struct Config {
+    /// Modifier standing for `Mod` in the bindings.
+    pub modifier: u32,
+    pub border_width: u32,
+    pub border_color: u64,
+    pub urgent_color: u64,
+    pub bg_color: u64,
+    /// Names of the workspaces. There is at least one.
+    pub workspaces: Vec<String>,
+    pub workspace_mode: WorkspaceMode,
+    /// Initial layout settings of every workspace.
+    pub tiling: bool,
+    pub layout: String,
+    pub layout_params: LayoutParams,
+    /// Instance or class names (from WM_CLASS) of the windows that turn
+    /// presentation mode on while they are mapped.
+    pub presentation_classes: Vec<String>,
+    pub focus_model: FocusModel,
+    /// Whether windows focused with the mouse are raised.
+    pub raise_on_focus: bool,
+    pub keys: Vec<KeyBinding>,
+    pub buttons: Vec<ButtonBinding>,
+}
+
+/// The configuration file as written by the user, before validation.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    border_width: Option<u32>,
+    border_color: Option<String>,
+    urgent_color: Option<String>,
+    bg_color: Option<String>,
+    modifier: Option<String>,
+    workspaces: Option<Vec<String>>,
+    workspace_mode: Option<String>,
+    tiling: Option<bool>,
+    layout: Option<String>,
+    master_count: Option<usize>,
+    master_ratio: Option<f32>,
+    presentation_classes: Option<Vec<String>>,
+    focus_model: Option<String>,
+    raise_on_focus: Option<bool>,
+    keys: Option<BTreeMap<String, String>>,
+    buttons: Option<BTreeMap<String, String>>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::from_raw(RawConfig::default()).expect("invalid default configuration")
+    }
+}
+
+impl Config {
+    /// Location of the configuration file.
+    pub fn path() -> Option<PathBuf> {
+        match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("rswm/config.toml")),
+            _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/rswm/config.toml")),
+        }
+    }
+
+    /// Load the configuration file, or the defaults if there is none.
+    pub fn load() -> Result<Config, ConfigError> {
+        match Config::path() {
+            Some(path) if path.exists() => Config::load_from(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        let raw: RawConfig = toml::from_str(&text).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?;
+        Config::from_raw(raw)
+    }
+
+    fn from_raw(raw: RawConfig) -> Result<Config, ConfigError> {
+        let modifier = match raw.modifier {
+            None => xlib::ControlMask,
+            Some(name) => parse_modifier(&name, 0)?,
+        };
+
+        let default_table = |table: &[(&str, &str)]| -> BTreeMap<String, String> {
+            table.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+
+        let workspaces = raw.workspaces
+            .unwrap_or_else(|| DEFAULT_WORKSPACES.iter().map(|name| name.to_string()).collect());
+        if workspaces.is_empty() {
+            return Err(ConfigError::Invalid("at least one workspace is required".to_string()));
+        }
+        let workspace_mode = match raw.workspace_mode {
+            None => WorkspaceMode::Global,
+            Some(mode) => mode.parse()?,
+        };
+        let focus_model = match raw.focus_model {
+            None => FocusModel::Click,
+            Some(model) => model.parse()?,
+        };
+
+        let layout = raw.layout.unwrap_or_else(|| "tile".to_string());
+        if !layout::all().iter().any(|l| l.name() == layout) {
+            return Err(ConfigError::Invalid(format!("unknown layout '{}'", layout)));
+        }
+        let master_ratio = raw.master_ratio.unwrap_or(0.55);
+        if !(0.05..=0.95).contains(&master_ratio) {
+            return Err(ConfigError::Invalid(format!("master_ratio {} is not between 0.05 and 0.95", master_ratio)));
+        }
+
+        let mut keys = Vec::new();
+        // The default bindings cover nine workspaces, whatever their number.
+        let default_keys = raw.keys.is_none();
+        for (combo, action) in raw.keys.unwrap_or_else(|| default_table(&DEFAULT_KEYS)) {
+            let (modifiers, key) = parse_combo(&combo, modifier)?;
+            let keysym = parse_keysym(key)
+                .ok_or_else(|| ConfigError::Invalid(format!("unknown key '{}' in '{}'", key, combo)))?;
+            let action: Action = action.parse().map_err(ConfigError::Invalid)?;
+            match &action {
+                Action::SwitchWorkspace(n) | Action::MoveToWorkspace(n) if *n >= workspaces.len() => {
+                    if default_keys {
+                        continue;
+                    }
+                    return Err(ConfigError::Invalid(format!("no workspace {} for '{}'", n + 1, combo)));
+                },
+                Action::SetLayout(name) if !layout::all().iter().any(|l| l.name() == name) =>
+                    return Err(ConfigError::Invalid(format!("unknown layout '{}' for '{}'", name, combo))),
+                _ => {}
+            }
+            keys.push(KeyBinding { modifiers, keysym, action });
+        }
+
+        let mut buttons = Vec::new();
+        for (combo, action) in raw.buttons.unwrap_or_else(|| default_table(&DEFAULT_BUTTONS)) {
+            let (modifiers, name) = parse_combo(&combo, modifier)?;
+            let button = parse_button(name)
+                .ok_or_else(|| ConfigError::Invalid(format!("unknown button '{}' in '{}'", name, combo)))?;
+            buttons.push(ButtonBinding { modifiers, button, action: action.parse()? });
+        }
+
+        Ok(Config {
+            modifier,
+            border_width: raw.border_width.unwrap_or(3),
+            border_color: parse_color(raw.border_color.as_deref().unwrap_or("#ff0000"))?,
+            urgent_color: parse_color(raw.urgent_color.as_deref().unwrap_or("#ffff00"))?,
+            bg_color: parse_color(raw.bg_color.as_deref().unwrap_or("#0000ff"))?,
+            workspaces,
+            workspace_mode,
+            tiling: raw.tiling.unwrap_or(false),
+            layout,
+            layout_params: LayoutParams { master_count: raw.master_count.unwrap_or(1), master_ratio },
+            presentation_classes: raw.presentation_classes.unwrap_or_default(),
+            focus_model,
+            raise_on_focus: raw.raise_on_focus.unwrap_or(true),
+            keys,
+            buttons,
+        })
+    }
+}
+
+/// Parse a color written as `#rrggbb`.
+fn parse_color(s: &str) -> Result<u64, ConfigError> {
+    let invalid = || ConfigError::Invalid(format!("invalid color '{}', expected #rrggbb", s));
+    match s.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 => u64::from_str_radix(hex, 16).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_modifier(name: &str, default_modifier: u32) -> Result<u32, ConfigError> {
+    match name {
+        "Mod" if default_modifier != 0 => Ok(default_modifier),
+        "Shift" => Ok(xlib::ShiftMask),
+        "Lock" => Ok(xlib::LockMask),
+        "Control" | "Ctrl" => Ok(xlib::ControlMask),
+        "Mod1" | "Alt" => Ok(xlib::Mod1Mask),
+        "Mod2" => Ok(xlib::Mod2Mask),
+        "Mod3" => Ok(xlib::Mod3Mask),
+        "Mod4" | "Super" => Ok(xlib::Mod4Mask),
+        "Mod5" => Ok(xlib::Mod5Mask),
+        _ => Err(ConfigError::Invalid(format!("unknown modifier '{}'", name))),
+    }
+}
+
+/// Split a binding such as `Mod+Shift+Return` into its modifier mask and its
+/// key or button name.
+fn parse_combo(combo: &str, default_modifier: u32) -> Result<(u32, &str), ConfigError> {
+    let mut parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+    let name = parts.pop().filter(|name| !name.is_empty())
+        .ok_or_else(|| ConfigError::Invalid(format!("invalid binding '{}'", combo)))?;
+    let mut modifiers = 0;
+    for part in parts {
+        modifiers |= parse_modifier(part, default_modifier)?;
+    }
+    Ok((modifiers, name))
+}
+
+fn parse_keysym(name: &str) -> Option<xlib::KeySym> {
+    let cstring = std::ffi::CString::new(name).ok()?;
+    match unsafe { xlib::XStringToKeysym(cstring.as_ptr()) } {
+        0 => None,
+        keysym => Some(keysym),
+    }
+}
+
+fn parse_button(name: &str) -> Option<u32> {
+    match name {
+        "Button1" => Some(xlib::Button1),
+        "Button2" => Some(xlib::Button2),
+        "Button3" => Some(xlib::Button3),
+        "Button4" => Some(xlib::Button4),
+        "Button5" => Some(xlib::Button5),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Config, ConfigError> {
+        Config::from_raw(toml::from_str(text).map_err(|err| ConfigError::Parse(PathBuf::new(), err))?)
+    }
+
+    fn error(text: &str) -> String {
+        match parse(text) {
+            Ok(_) => panic!("accepted invalid configuration {:?}", text),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn default_keys_skip_missing_workspaces() {
+        let config = parse(r#"workspaces = ["web", "code", "chat"]"#).unwrap();
+        let workspace_actions: Vec<&Action> = config.keys.iter()
+            .map(|binding| &binding.action)
+            .filter(|action| matches!(action, Action::SwitchWorkspace(_) | Action::MoveToWorkspace(_)))
+            .collect();
+        assert_eq!(workspace_actions.len(), 6);
+        assert!(workspace_actions.iter().all(|action| match action {
+            Action::SwitchWorkspace(n) | Action::MoveToWorkspace(n) => *n < 3,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn user_keys_must_match_workspaces() {
+        let text = "workspaces = [\"web\", \"code\"]\n[keys]\n\"Mod+3\" = \"workspace 3\"";
+        assert_eq!(error(text), "no workspace 3 for 'Mod+3'");
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("#ff8000").unwrap(), 0xff8000);
+        assert!(parse_color("ff8000").is_err());
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("#gg8000").is_err());
+    }
+
+    #[test]
+    fn mod_stands_for_the_configured_modifier() {
+        assert_eq!(parse_combo("Mod+Shift+Return", xlib::Mod4Mask).unwrap(), (xlib::Mod4Mask | xlib::ShiftMask, "Return"));
+        assert_eq!(parse_combo("Alt+Button1", xlib::Mod4Mask).unwrap(), (xlib::Mod1Mask, "Button1"));
+        let config = parse("modifier = \"Super\"\n[keys]\n\"Mod+Tab\" = \"focus_next\"").unwrap();
+        assert_eq!(config.modifier, xlib::Mod4Mask);
+        assert_eq!(config.keys[0].modifiers, xlib::Mod4Mask);
+        // Mod cannot define itself.
+        assert_eq!(error("modifier = \"Mod\""), "unknown modifier 'Mod'");
+    }
+
+    #[test]
+    fn reports_invalid_bindings() {
+        assert_eq!(error("[keys]\n\"Hyper+x\" = \"close\""), "unknown modifier 'Hyper'");
+        assert_eq!(error("[keys]\n\"Mod+NoSuchKey\" = \"close\""), "unknown key 'NoSuchKey' in 'Mod+NoSuchKey'");
+        assert_eq!(error("[keys]\n\"Mod+\" = \"close\""), "invalid binding 'Mod+'");
+        assert_eq!(error("[buttons]\n\"Mod+Button9\" = \"move\""), "unknown button 'Button9' in 'Mod+Button9'");
+        assert_eq!(error("[buttons]\n\"Mod+Button1\" = \"drag\""), "unknown button action 'drag'");
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        assert!(error("border_colour = \"#ffffff\"").contains("unknown field `border_colour`"));
+    }
+
+    #[test]
+    fn bounds_master_ratio() {
+        assert!(parse("master_ratio = 0.05").is_ok());
+        assert!(parse("master_ratio = 0.95").is_ok());
+        assert_eq!(error("master_ratio = 0.99"), "master_ratio 0.99 is not between 0.05 and 0.95");
+        assert!(parse("master_ratio = 0.01").is_err());
+    }
+}
@@ -0,0 +1,414 @@
+//! Extended Window Manager Hints.
+//!
+//! Properties set on the root window so that panels, pagers and tools such as
+//! `wmctrl` can find the window manager and the windows it manages.
+
+use std::ffi::CString;
+use std::os::raw;
+use x11::xlib;
+use crate::layout::Rect;
+
+/// Atoms of the hints we support, interned once at startup.
+pub struct Atoms {
+    pub utf8_string: xlib::Atom,
+    pub net_supported: xlib::Atom,
+    pub net_supporting_wm_check: xlib::Atom,
+    pub net_wm_name: xlib::Atom,
+    pub net_client_list: xlib::Atom,
+    pub net_client_list_stacking: xlib::Atom,
+    pub net_active_window: xlib::Atom,
+    pub net_number_of_desktops: xlib::Atom,
+    pub net_current_desktop: xlib::Atom,
+    pub net_desktop_names: xlib::Atom,
+    pub net_desktop_viewport: xlib::Atom,
+    pub net_wm_desktop: xlib::Atom,
+    pub net_wm_state: xlib::Atom,
+    pub net_wm_state_fullscreen: xlib::Atom,
+    pub net_wm_state_maximized_vert: xlib::Atom,
+    pub net_wm_state_maximized_horz: xlib::Atom,
+    pub net_wm_state_above: xlib::Atom,
+    pub net_wm_state_below: xlib::Atom,
+    pub net_wm_state_sticky: xlib::Atom,
+    pub net_wm_state_hidden: xlib::Atom,
+    pub net_wm_state_demands_attention: xlib::Atom,
+    pub net_wm_window_type: xlib::Atom,
+    pub net_wm_window_type_desktop: xlib::Atom,
+    pub net_wm_window_type_dock: xlib::Atom,
+    pub net_wm_window_type_dialog: xlib::Atom,
+    pub net_wm_window_type_splash: xlib::Atom,
+    pub net_wm_window_type_notification: xlib::Atom,
+    pub net_wm_window_type_tooltip: xlib::Atom,
+    pub net_wm_window_type_normal: xlib::Atom,
+    pub net_wm_strut: xlib::Atom,
+    pub net_wm_strut_partial: xlib::Atom,
+    pub net_workarea: xlib::Atom,
+}
+
+fn intern(display: *mut xlib::Display, name: &str) -> xlib::Atom {
+    let name = CString::new(name).expect("CString::new() failed");
+    unsafe { xlib::XInternAtom(display, name.as_ptr(), 0) }
+}
+
+impl Atoms {
+    pub fn intern(display: *mut xlib::Display) -> Atoms {
+        Atoms {
+            utf8_string: intern(display, "UTF8_STRING"),
+            net_supported: intern(display, "_NET_SUPPORTED"),
+            net_supporting_wm_check: intern(display, "_NET_SUPPORTING_WM_CHECK"),
+            net_wm_name: intern(display, "_NET_WM_NAME"),
+            net_client_list: intern(display, "_NET_CLIENT_LIST"),
+            net_client_list_stacking: intern(display, "_NET_CLIENT_LIST_STACKING"),
+            net_active_window: intern(display, "_NET_ACTIVE_WINDOW"),
+            net_number_of_desktops: intern(display, "_NET_NUMBER_OF_DESKTOPS"),
+            net_current_desktop: intern(display, "_NET_CURRENT_DESKTOP"),
+            net_desktop_names: intern(display, "_NET_DESKTOP_NAMES"),
+            net_desktop_viewport: intern(display, "_NET_DESKTOP_VIEWPORT"),
+            net_wm_desktop: intern(display, "_NET_WM_DESKTOP"),
+            net_wm_state: intern(display, "_NET_WM_STATE"),
+            net_wm_state_fullscreen: intern(display, "_NET_WM_STATE_FULLSCREEN"),
+            net_wm_state_maximized_vert: intern(display, "_NET_WM_STATE_MAXIMIZED_VERT"),
+            net_wm_state_maximized_horz: intern(display, "_NET_WM_STATE_MAXIMIZED_HORZ"),
+            net_wm_state_above: intern(display, "_NET_WM_STATE_ABOVE"),
+            net_wm_state_below: intern(display, "_NET_WM_STATE_BELOW"),
+            net_wm_state_sticky: intern(display, "_NET_WM_STATE_STICKY"),
+            net_wm_state_hidden: intern(display, "_NET_WM_STATE_HIDDEN"),
+            net_wm_state_demands_attention: intern(display, "_NET_WM_STATE_DEMANDS_ATTENTION"),
+            net_wm_window_type: intern(display, "_NET_WM_WINDOW_TYPE"),
+            net_wm_window_type_desktop: intern(display, "_NET_WM_WINDOW_TYPE_DESKTOP"),
+            net_wm_window_type_dock: intern(display, "_NET_WM_WINDOW_TYPE_DOCK"),
+            net_wm_window_type_dialog: intern(display, "_NET_WM_WINDOW_TYPE_DIALOG"),
+            net_wm_window_type_splash: intern(display, "_NET_WM_WINDOW_TYPE_SPLASH"),
+            net_wm_window_type_notification: intern(display, "_NET_WM_WINDOW_TYPE_NOTIFICATION"),
+            net_wm_window_type_tooltip: intern(display, "_NET_WM_WINDOW_TYPE_TOOLTIP"),
+            net_wm_window_type_normal: intern(display, "_NET_WM_WINDOW_TYPE_NORMAL"),
+            net_wm_strut: intern(display, "_NET_WM_STRUT"),
+            net_wm_strut_partial: intern(display, "_NET_WM_STRUT_PARTIAL"),
+            net_workarea: intern(display, "_NET_WORKAREA"),
+        }
+    }
+
+    /// Hints advertised in _NET_SUPPORTED.
+    pub fn supported(&self) -> Vec<xlib::Atom> {
+        vec![
+            self.net_supported,
+            self.net_supporting_wm_check,
+            self.net_wm_name,
+            self.net_client_list,
+            self.net_client_list_stacking,
+            self.net_active_window,
+            self.net_number_of_desktops,
+            self.net_current_desktop,
+            self.net_desktop_names,
+            self.net_desktop_viewport,
+            self.net_wm_desktop,
+            self.net_wm_state,
+            self.net_wm_state_fullscreen,
+            self.net_wm_state_maximized_vert,
+            self.net_wm_state_maximized_horz,
+            self.net_wm_state_above,
+            self.net_wm_state_below,
+            self.net_wm_state_sticky,
+            self.net_wm_state_hidden,
+            self.net_wm_state_demands_attention,
+            self.net_wm_window_type,
+            self.net_wm_window_type_desktop,
+            self.net_wm_window_type_dock,
+            self.net_wm_window_type_dialog,
+            self.net_wm_window_type_splash,
+            self.net_wm_window_type_notification,
+            self.net_wm_window_type_tooltip,
+            self.net_wm_window_type_normal,
+            self.net_wm_strut,
+            self.net_wm_strut_partial,
+            self.net_workarea,
+        ]
+    }
+}
+
+/// Source indication of client messages sent by an application, as opposed to
+/// a pager or taskbar acting on behalf of the user.
+pub const SOURCE_APPLICATION: i64 = 1;
+
+/// Functional type of a window, which decides how it is managed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowType {
+    Normal,
+    /// Panel reserving space at an edge of the screen, left unframed and kept
+    /// above the clients.
+    Dock,
+    /// Window showing the desktop icons or background, left unframed and kept
+    /// below the clients.
+    Desktop,
+    /// Notification bubble or tooltip, shown but not managed.
+    Notification,
+    /// Dialog or splash screen, framed but floating and centered.
+    Dialog,
+}
+
+/// Type of window `w`, from its _NET_WM_WINDOW_TYPE property. The property
+/// lists types by order of preference, the first one we know wins.
+pub fn window_type(display: *mut xlib::Display, w: xlib::Window, atoms: &Atoms) -> WindowType {
+    for atom in get_atoms(display, w, atoms.net_wm_window_type) {
+        if atom == atoms.net_wm_window_type_dock {
+            return WindowType::Dock;
+        } else if atom == atoms.net_wm_window_type_desktop {
+            return WindowType::Desktop;
+        } else if atom == atoms.net_wm_window_type_notification || atom == atoms.net_wm_window_type_tooltip {
+            return WindowType::Notification;
+        } else if atom == atoms.net_wm_window_type_dialog || atom == atoms.net_wm_window_type_splash {
+            return WindowType::Dialog;
+        } else if atom == atoms.net_wm_window_type_normal {
+            return WindowType::Normal;
+        }
+    }
+    WindowType::Normal
+}
+
+/// Space reserved by a window at the edges of the screen. Each edge is
+/// reserved along a range of the edge, for instance from `top_start_x` to
+/// `top_end_x` (included) for the top edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Strut {
+    pub left: u64,
+    pub right: u64,
+    pub top: u64,
+    pub bottom: u64,
+    pub left_start_y: u64,
+    pub left_end_y: u64,
+    pub right_start_y: u64,
+    pub right_end_y: u64,
+    pub top_start_x: u64,
+    pub top_end_x: u64,
+    pub bottom_start_x: u64,
+    pub bottom_end_x: u64,
+}
+
+impl Strut {
+    /// Strut of window `w`, from _NET_WM_STRUT_PARTIAL or else from the older
+    /// _NET_WM_STRUT, which reserves whole edges.
+    pub fn read(display: *mut xlib::Display, w: xlib::Window, atoms: &Atoms, screen: Rect) -> Option<Strut> {
+        let partial = get_cardinals(display, w, atoms.net_wm_strut_partial);
+        if partial.len() >= 12 {
+            return Some(Strut {
+                left: partial[0],
+                right: partial[1],
+                top: partial[2],
+                bottom: partial[3],
+                left_start_y: partial[4],
+                left_end_y: partial[5],
+                right_start_y: partial[6],
+                right_end_y: partial[7],
+                top_start_x: partial[8],
+                top_end_x: partial[9],
+                bottom_start_x: partial[10],
+                bottom_end_x: partial[11],
+            });
+        }
+        let full = get_cardinals(display, w, atoms.net_wm_strut);
+        if full.len() >= 4 {
+            return Some(Strut::whole_edges(full[0], full[1], full[2], full[3], screen));
+        }
+        None
+    }
+
+    fn whole_edges(left: u64, right: u64, top: u64, bottom: u64, screen: Rect) -> Strut {
+        let (max_x, max_y) = (screen.width as u64 - 1, screen.height as u64 - 1);
+        Strut {
+            left,
+            right,
+            top,
+            bottom,
+            left_end_y: max_y,
+            right_end_y: max_y,
+            top_end_x: max_x,
+            bottom_end_x: max_x,
+            ..Default::default()
+        }
+    }
+
+    /// Strut of a dock without strut property, guessed from its geometry: a
+    /// dock wider than tall is a bar along the top or bottom edge of the
+    /// screen, whichever is closer. Otherwise it is along the left or right
+    /// edge. Only the part of the edge the dock runs along is reserved.
+    pub fn from_geometry(dock: Rect, screen: Rect) -> Strut {
+        let (width, height) = (screen.width as i32, screen.height as i32);
+        let (dock_width, dock_height) = (dock.width as i32, dock.height as i32);
+        let reserved = |n: i32| std::cmp::max(n, 0) as u64;
+        let (start_x, end_x) = (reserved(dock.x), reserved(dock.x + dock_width - 1));
+        let (start_y, end_y) = (reserved(dock.y), reserved(dock.y + dock_height - 1));
+        if dock_width >= dock_height {
+            if dock.y + dock_height / 2 < height / 2 {
+                Strut { top: reserved(dock.y + dock_height), top_start_x: start_x, top_end_x: end_x, ..Default::default() }
+            } else {
+                Strut { bottom: reserved(height - dock.y), bottom_start_x: start_x, bottom_end_x: end_x, ..Default::default() }
+            }
+        } else if dock.x + dock_width / 2 < width / 2 {
+            Strut { left: reserved(dock.x + dock_width), left_start_y: start_y, left_end_y: end_y, ..Default::default() }
+        } else {
+            Strut { right: reserved(width - dock.x), right_start_y: start_y, right_end_y: end_y, ..Default::default() }
+        }
+    }
+}
+
+/// Part of `monitor`, a part of `screen`, that is not reserved by `struts`.
+/// Strut edges only apply to the monitors they run along.
+pub fn work_area(monitor: Rect, screen: Rect, struts: &[Strut]) -> Rect {
+    let overlaps = |start: u64, end: u64, from: i32, length: u32| {
+        (start as i64) < from as i64 + length as i64 && end as i64 >= from as i64
+    };
+    let (mut left, mut right) = (monitor.x as i64, monitor.x as i64 + monitor.width as i64);
+    let (mut top, mut bottom) = (monitor.y as i64, monitor.y as i64 + monitor.height as i64);
+    let (screen_right, screen_bottom) = (screen.width as i64, screen.height as i64);
+    for strut in struts {
+        if strut.left > 0 && overlaps(strut.left_start_y, strut.left_end_y, monitor.y, monitor.height) {
+            left = std::cmp::max(left, strut.left as i64);
+        }
+        if strut.right > 0 && overlaps(strut.right_start_y, strut.right_end_y, monitor.y, monitor.height) {
+            right = std::cmp::min(right, screen_right - strut.right as i64);
+        }
+        if strut.top > 0 && overlaps(strut.top_start_x, strut.top_end_x, monitor.x, monitor.width) {
+            top = std::cmp::max(top, strut.top as i64);
+        }
+        if strut.bottom > 0 && overlaps(strut.bottom_start_x, strut.bottom_end_x, monitor.x, monitor.width) {
+            bottom = std::cmp::min(bottom, screen_bottom - strut.bottom as i64);
+        }
+    }
+    Rect {
+        x: left as i32,
+        y: top as i32,
+        width: std::cmp::max(right - left, 1) as u32,
+        height: std::cmp::max(bottom - top, 1) as u32,
+    }
+}
+
+/// Actions of _NET_WM_STATE client messages.
+pub const STATE_REMOVE: i64 = 0;
+pub const STATE_ADD: i64 = 1;
+pub const STATE_TOGGLE: i64 = 2;
+
+/// Value of _NET_WM_DESKTOP for windows shown on every desktop.
+pub const ALL_DESKTOPS: u64 = 0xFFFFFFFF;
+
+/// Set property `property` of window `w` to a list of 32-bit values of type
+/// `type_`.
+fn set_list(display: *mut xlib::Display, w: xlib::Window, property: xlib::Atom, type_: xlib::Atom, values: &[u64]) {
+    // Xlib expects format 32 data as an array of longs.
+    let data: Vec<libc::c_long> = values.iter().map(|&v| v as libc::c_long).collect();
+    unsafe {
+        xlib::XChangeProperty(
+            display,
+            w,
+            property,
+            type_,
+            32,
+            xlib::PropModeReplace,
+            data.as_ptr() as *const u8,
+            data.len() as i32);
+    }
+}
+
+/// Values of 32-bit property `property` of window `w`, of type `type_`. Empty if
+/// the property is not set or has another type.
+fn get_list(display: *mut xlib::Display, w: xlib::Window, property: xlib::Atom, type_: xlib::Atom) -> Vec<u64> {
+    let mut actual_type: xlib::Atom = 0;
+    let mut actual_format = 0;
+    let mut n: raw::c_ulong = 0;
+    let mut bytes_after: raw::c_ulong = 0;
+    let mut data: *mut u8 = std::ptr::null_mut();
+    unsafe {
+        let status = xlib::XGetWindowProperty(
+            display,
+            w,
+            property,
+            0,
+            1024,
+            0,
+            type_,
+            &mut actual_type,
+            &mut actual_format,
+            &mut n,
+            &mut bytes_after,
+            &mut data);
+        if status != xlib::Success as i32 || data.is_null() {
+            return Vec::new();
+        }
+        let values = if actual_type == type_ && actual_format == 32 {
+            // Format 32 data is returned as an array of longs.
+            std::slice::from_raw_parts(data as *const raw::c_ulong, n as usize).to_vec()
+        } else {
+            Vec::new()
+        };
+        xlib::XFree(data as *mut std::ffi::c_void);
+        values
+    }
+}
+
+pub fn get_cardinals(display: *mut xlib::Display, w: xlib::Window, property: xlib::Atom) -> Vec<u64> {
+    get_list(display, w, property, xlib::XA_CARDINAL)
+}
+
+pub fn get_atoms(display: *mut xlib::Display, w: xlib::Window, property: xlib::Atom) -> Vec<xlib::Atom> {
+    get_list(display, w, property, xlib::XA_ATOM)
+}
+
+pub fn set_cardinals(display: *mut xlib::Display, w: xlib::Window, property: xlib::Atom, values: &[u64]) {
+    set_list(display, w, property, xlib::XA_CARDINAL, values);
+}
+
+pub fn set_windows(display: *mut xlib::Display, w: xlib::Window, property: xlib::Atom, windows: &[xlib::Window]) {
+    set_list(display, w, property, xlib::XA_WINDOW, windows);
+}
+
+pub fn set_atoms(display: *mut xlib::Display, w: xlib::Window, property: xlib::Atom, atoms: &[xlib::Atom]) {
+    set_list(display, w, property, xlib::XA_ATOM, atoms);
+}
+
+pub fn set_string(display: *mut xlib::Display, w: xlib::Window, atoms: &Atoms, property: xlib::Atom, value: &str) {
+    unsafe {
+        xlib::XChangeProperty(
+            display,
+            w,
+            property,
+            atoms.utf8_string,
+            8,
+            xlib::PropModeReplace,
+            value.as_ptr(),
+            value.len() as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two monitors side by side.
+    const SCREEN: Rect = Rect { x: 0, y: 0, width: 3840, height: 1080 };
+    const LEFT: Rect = Rect { x: 0, y: 0, width: 1920, height: 1080 };
+    const RIGHT: Rect = Rect { x: 1920, y: 0, width: 1920, height: 1080 };
+
+    #[test]
+    fn dock_without_strut_reserves_its_own_monitor() {
+        let strut = Strut::from_geometry(Rect { x: 0, y: 1050, width: 1920, height: 30 }, SCREEN);
+        assert_eq!(strut, Strut { bottom: 30, bottom_start_x: 0, bottom_end_x: 1919, ..Default::default() });
+        assert_eq!(work_area(LEFT, SCREEN, &[strut]), Rect { height: 1050, ..LEFT });
+        assert_eq!(work_area(RIGHT, SCREEN, &[strut]), RIGHT);
+    }
+
+    #[test]
+    fn guesses_the_closest_edge() {
+        let top = Strut::from_geometry(Rect { x: 1920, y: 0, width: 1920, height: 20 }, SCREEN);
+        assert_eq!(top, Strut { top: 20, top_start_x: 1920, top_end_x: 3839, ..Default::default() });
+        let right = Strut::from_geometry(Rect { x: 3790, y: 100, width: 50, height: 800 }, SCREEN);
+        assert_eq!(right, Strut { right: 50, right_start_y: 100, right_end_y: 899, ..Default::default() });
+    }
+
+    #[test]
+    fn partial_struts_apply_to_the_monitors_they_run_along() {
+        let left_panel = Strut { left: 40, left_start_y: 0, left_end_y: 1079, ..Default::default() };
+        let right_bar = Strut { top: 25, top_start_x: 1920, top_end_x: 3839, ..Default::default() };
+        let struts = [left_panel, right_bar];
+        assert_eq!(work_area(LEFT, SCREEN, &struts), Rect { x: 40, width: 1880, ..LEFT });
+        assert_eq!(work_area(RIGHT, SCREEN, &struts), Rect { y: 25, height: 1055, ..RIGHT });
+        assert_eq!(work_area(SCREEN, SCREEN, &struts), Rect { x: 40, y: 25, width: 3800, height: 1055 });
+    }
+}
@@ -0,0 +1,188 @@
+//! ICCCM hints set by clients on their windows.
+
+use x11::xlib;
+
+/// Value of the initial_state field of WM_HINTS for iconified windows.
+const ICONIC_STATE: i32 = 3;
+
+/// Hints from the WM_HINTS property of a window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WmHints {
+    /// Whether the client relies on the window manager to set the input
+    /// focus. Clients that don't may still ask for WM_TAKE_FOCUS messages.
+    pub input: bool,
+    pub urgent: bool,
+    /// Whether the window asks to start iconified.
+    pub iconic: bool,
+}
+
+impl Default for WmHints {
+    fn default() -> WmHints {
+        WmHints { input: true, urgent: false, iconic: false }
+    }
+}
+
+impl WmHints {
+    pub fn read(display: *mut xlib::Display, w: xlib::Window) -> WmHints {
+        let mut wm_hints = WmHints::default();
+        unsafe {
+            let hints = xlib::XGetWMHints(display, w);
+            if hints.is_null() {
+                return wm_hints;
+            }
+            if (*hints).flags & xlib::InputHint != 0 {
+                wm_hints.input = (*hints).input != 0;
+            }
+            wm_hints.urgent = (*hints).flags & xlib::XUrgencyHint != 0;
+            wm_hints.iconic = (*hints).flags & xlib::StateHint != 0 && (*hints).initial_state == ICONIC_STATE;
+            xlib::XFree(hints as *mut std::ffi::c_void);
+        }
+        wm_hints
+    }
+}
+
+/// Constraints on the size of a window, from its WM_NORMAL_HINTS property.
+/// Sizes are those of the client window, without the frame border. A zero
+/// value means no constraint.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SizeHints {
+    pub base_width: i32,
+    pub base_height: i32,
+    pub min_width: i32,
+    pub min_height: i32,
+    pub max_width: i32,
+    pub max_height: i32,
+    pub width_inc: i32,
+    pub height_inc: i32,
+    /// Bounds of the width / height ratio.
+    pub min_aspect: f32,
+    pub max_aspect: f32,
+}
+
+impl SizeHints {
+    pub fn read(display: *mut xlib::Display, w: xlib::Window) -> SizeHints {
+        let mut hints: xlib::XSizeHints = unsafe { std::mem::zeroed() };
+        let mut supplied = 0;
+        if unsafe { xlib::XGetWMNormalHints(display, w, &mut hints, &mut supplied) } == 0 {
+            return SizeHints::default();
+        }
+        let mut size_hints = SizeHints::default();
+        // The base size defaults to the minimum size and conversely.
+        if hints.flags & xlib::PBaseSize != 0 {
+            size_hints.base_width = hints.base_width;
+            size_hints.base_height = hints.base_height;
+        } else if hints.flags & xlib::PMinSize != 0 {
+            size_hints.base_width = hints.min_width;
+            size_hints.base_height = hints.min_height;
+        }
+        if hints.flags & xlib::PMinSize != 0 {
+            size_hints.min_width = hints.min_width;
+            size_hints.min_height = hints.min_height;
+        } else if hints.flags & xlib::PBaseSize != 0 {
+            size_hints.min_width = hints.base_width;
+            size_hints.min_height = hints.base_height;
+        }
+        if hints.flags & xlib::PMaxSize != 0 {
+            size_hints.max_width = hints.max_width;
+            size_hints.max_height = hints.max_height;
+        }
+        if hints.flags & xlib::PResizeInc != 0 {
+            size_hints.width_inc = hints.width_inc;
+            size_hints.height_inc = hints.height_inc;
+        }
+        if hints.flags & xlib::PAspect != 0 && hints.min_aspect.y > 0 && hints.max_aspect.y > 0 {
+            size_hints.min_aspect = hints.min_aspect.x as f32 / hints.min_aspect.y as f32;
+            size_hints.max_aspect = hints.max_aspect.x as f32 / hints.max_aspect.y as f32;
+        }
+        size_hints
+    }
+
+    /// The size closest to `width` x `height` that satisfies the hints.
+    pub fn constrain(&self, width: u32, height: u32) -> (u32, u32) {
+        let (mut width, mut height) = (width as i32, height as i32);
+        // 1. The aspect ratio and increments apply to the size above the base
+        // size, unless the base size is only the minimum size.
+        let base_is_min = self.base_width == self.min_width && self.base_height == self.min_height;
+        if !base_is_min {
+            width -= self.base_width;
+            height -= self.base_height;
+        }
+        // 2. Keep the aspect ratio within bounds.
+        if self.min_aspect > 0.0 && self.max_aspect > 0.0 && width > 0 && height > 0 {
+            let aspect = width as f32 / height as f32;
+            if aspect > self.max_aspect {
+                width = (height as f32 * self.max_aspect + 0.5) as i32;
+            } else if aspect < self.min_aspect {
+                height = (width as f32 / self.min_aspect + 0.5) as i32;
+            }
+        }
+        if base_is_min {
+            width -= self.base_width;
+            height -= self.base_height;
+        }
+        // 3. Round down to a whole number of increments.
+        if self.width_inc > 0 {
+            width -= width.rem_euclid(self.width_inc);
+        }
+        if self.height_inc > 0 {
+            height -= height.rem_euclid(self.height_inc);
+        }
+        // 4. Stay within the minimum and maximum sizes.
+        width = std::cmp::max(width + self.base_width, self.min_width);
+        height = std::cmp::max(height + self.base_height, self.min_height);
+        if self.max_width > 0 {
+            width = std::cmp::min(width, self.max_width);
+        }
+        if self.max_height > 0 {
+            height = std::cmp::min(height, self.max_height);
+        }
+        (std::cmp::max(width, 1) as u32, std::cmp::max(height, 1) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_terminals_to_whole_cells_above_base_size() {
+        let hints = SizeHints {
+            base_width: 4,
+            base_height: 4,
+            min_width: 13,
+            min_height: 21,
+            width_inc: 9,
+            height_inc: 17,
+            ..SizeHints::default()
+        };
+        // 88 columns and 35 rows.
+        assert_eq!(hints.constrain(800, 600), (4 + 88 * 9, 4 + 35 * 17));
+    }
+
+    #[test]
+    fn grows_to_minimum_size() {
+        let hints = SizeHints { min_width: 200, min_height: 100, base_width: 200, base_height: 100, ..SizeHints::default() };
+        assert_eq!(hints.constrain(50, 50), (200, 100));
+    }
+
+    #[test]
+    fn shrinks_to_maximum_size() {
+        let hints = SizeHints { max_width: 300, max_height: 200, ..SizeHints::default() };
+        assert_eq!(hints.constrain(800, 600), (300, 200));
+    }
+
+    #[test]
+    fn keeps_aspect_ratio_within_bounds() {
+        let hints = SizeHints { min_aspect: 1.0, max_aspect: 1.0, ..SizeHints::default() };
+        assert_eq!(hints.constrain(400, 200), (200, 200));
+        assert_eq!(hints.constrain(200, 400), (200, 200));
+        let hints = SizeHints { min_aspect: 0.5, max_aspect: 2.0, ..SizeHints::default() };
+        assert_eq!(hints.constrain(300, 200), (300, 200));
+    }
+
+    #[test]
+    fn without_hints_keeps_requested_size() {
+        assert_eq!(SizeHints::default().constrain(640, 480), (640, 480));
+        assert_eq!(SizeHints::default().constrain(0, 0), (1, 1));
+    }
+}
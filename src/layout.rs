@@ -0,0 +1,270 @@
+//! Automatic placement of the windows of a workspace.
+
+use x11::xlib;
+
+use crate::action::Direction;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn transpose(self) -> Rect {
+        Rect { x: self.y, y: self.x, width: self.height, height: self.width }
+    }
+}
+
+/// Settings of a layout that can be adjusted at runtime.
+#[derive(Clone, Copy, Debug)]
+pub struct LayoutParams {
+    /// Number of clients in the master area.
+    pub master_count: usize,
+    /// Part of the screen used by the master area, between 0 and 1.
+    pub master_ratio: f32,
+}
+
+pub trait Layout {
+    fn name(&self) -> &'static str;
+
+    /// Compute the rectangle of each client of `clients`, in the same order,
+    /// so that they fit in `area`.
+    fn arrange(&self, area: Rect, clients: &[xlib::Window], params: &LayoutParams) -> Vec<Rect>;
+}
+
+/// Every available layout.
+pub fn all() -> Vec<Box<dyn Layout>> {
+    vec![
+        Box::new(MasterStack { horizontal: false }),
+        Box::new(MasterStack { horizontal: true }),
+        Box::new(Monocle),
+        Box::new(Grid),
+    ]
+}
+
+/// Split `area` into `n` rows of (almost) equal height.
+fn split_rows(area: Rect, n: usize) -> Vec<Rect> {
+    let mut rects = Vec::with_capacity(n);
+    let mut y = area.y;
+    for i in 0..n {
+        // The last row takes the pixels left by the integer division.
+        let height = if i + 1 == n {
+            (area.y + area.height as i32 - y) as u32
+        } else {
+            area.height / n as u32
+        };
+        rects.push(Rect { x: area.x, y, width: area.width, height });
+        y += height as i32;
+    }
+    rects
+}
+
+/// Closest window among `candidates` in direction `direction` from window `w`
+/// with frame `from`, by the centers of their frames. Windows facing `w`, that
+/// is overlapping it across the direction, come first.
+pub fn neighbor(w: xlib::Window, from: Rect, direction: Direction, candidates: &[(xlib::Window, Rect)]) -> Option<xlib::Window> {
+    let center = |r: Rect| (r.x + r.width as i32 / 2, r.y + r.height as i32 / 2);
+    let (x, y) = center(from);
+    candidates.iter()
+        .copied()
+        .filter(|&(c, _)| c != w)
+        .filter_map(|(c, rect)| {
+            let (cx, cy) = center(rect);
+            let overlaps_x = rect.x < from.x + from.width as i32 && from.x < rect.x + rect.width as i32;
+            let overlaps_y = rect.y < from.y + from.height as i32 && from.y < rect.y + rect.height as i32;
+            let (along, across, facing) = match direction {
+                Direction::Left => (x - cx, (cy - y).abs(), overlaps_y),
+                Direction::Right => (cx - x, (cy - y).abs(), overlaps_y),
+                Direction::Up => (y - cy, (cx - x).abs(), overlaps_x),
+                Direction::Down => (cy - y, (cx - x).abs(), overlaps_x),
+            };
+            if along > 0 { Some((!facing, along, across, c)) } else { None }
+        })
+        .min()
+        .map(|(_, _, _, c)| c)
+}
+
+/// Master clients in one column on the left and the others stacked in a
+/// column on the right. The horizontal variant puts the master clients in a
+/// row at the top and the others in a row at the bottom.
+pub struct MasterStack {
+    horizontal: bool,
+}
+
+impl MasterStack {
+    fn arrange_vertical(area: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+        let masters = std::cmp::min(params.master_count, n);
+        let stacked = n - masters;
+        let master_width = if masters == 0 {
+            0
+        } else if stacked == 0 {
+            area.width
+        } else {
+            (area.width as f32 * params.master_ratio) as u32
+        };
+        let master_area = Rect { width: master_width, ..area };
+        let stack_area = Rect {
+            x: area.x + master_width as i32,
+            width: area.width - master_width,
+            ..area
+        };
+        let mut rects = split_rows(master_area, masters);
+        rects.extend(split_rows(stack_area, stacked));
+        rects
+    }
+}
+
+impl Layout for MasterStack {
+    fn name(&self) -> &'static str {
+        if self.horizontal { "bstack" } else { "tile" }
+    }
+
+    fn arrange(&self, area: Rect, clients: &[xlib::Window], params: &LayoutParams) -> Vec<Rect> {
+        if self.horizontal {
+            MasterStack::arrange_vertical(area.transpose(), clients.len(), params)
+                .into_iter()
+                .map(Rect::transpose)
+                .collect()
+        } else {
+            MasterStack::arrange_vertical(area, clients.len(), params)
+        }
+    }
+}
+
+/// Every client covers the whole area. Only the focused one, which is raised,
+/// is visible.
+pub struct Monocle;
+
+impl Layout for Monocle {
+    fn name(&self) -> &'static str {
+        "monocle"
+    }
+
+    fn arrange(&self, area: Rect, clients: &[xlib::Window], _: &LayoutParams) -> Vec<Rect> {
+        vec![area; clients.len()]
+    }
+}
+
+/// Clients in rows and columns, as close to a square grid as possible. The
+/// clients of an incomplete last row share its whole width.
+pub struct Grid;
+
+impl Layout for Grid {
+    fn name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn arrange(&self, area: Rect, clients: &[xlib::Window], _: &LayoutParams) -> Vec<Rect> {
+        let n = clients.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let columns = (n as f64).sqrt().ceil() as usize;
+        let rows = n.div_ceil(columns);
+        let mut rects = Vec::with_capacity(n);
+        for (row, row_area) in split_rows(area, rows).into_iter().enumerate() {
+            let in_row = std::cmp::min(columns, n - row * columns);
+            rects.extend(split_rows(row_area.transpose(), in_row).into_iter().map(Rect::transpose));
+        }
+        rects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect { x: 10, y: 20, width: 1000, height: 100 };
+
+    fn params(master_count: usize) -> LayoutParams {
+        LayoutParams { master_count, master_ratio: 0.6 }
+    }
+
+    #[test]
+    fn splits_master_and_stack_columns() {
+        assert_eq!(MasterStack::arrange_vertical(AREA, 3, &params(1)), vec![
+            Rect { x: 10, y: 20, width: 600, height: 100 },
+            Rect { x: 610, y: 20, width: 400, height: 50 },
+            Rect { x: 610, y: 70, width: 400, height: 50 },
+        ]);
+    }
+
+    #[test]
+    fn without_master_stacks_in_the_whole_area() {
+        assert_eq!(MasterStack::arrange_vertical(AREA, 2, &params(0)), vec![
+            Rect { x: 10, y: 20, width: 1000, height: 50 },
+            Rect { x: 10, y: 70, width: 1000, height: 50 },
+        ]);
+    }
+
+    #[test]
+    fn without_stacked_clients_masters_use_the_whole_area() {
+        let rects = vec![
+            Rect { x: 10, y: 20, width: 1000, height: 50 },
+            Rect { x: 10, y: 70, width: 1000, height: 50 },
+        ];
+        assert_eq!(MasterStack::arrange_vertical(AREA, 2, &params(2)), rects);
+        assert_eq!(MasterStack::arrange_vertical(AREA, 2, &params(5)), rects);
+    }
+
+    #[test]
+    fn last_row_takes_the_leftover_pixels() {
+        let heights: Vec<u32> = split_rows(AREA, 3).iter().map(|rect| rect.height).collect();
+        assert_eq!(heights, vec![33, 33, 34]);
+        assert_eq!(split_rows(AREA, 0), vec![]);
+    }
+
+    #[test]
+    fn bstack_puts_masters_at_the_top() {
+        let layout = MasterStack { horizontal: true };
+        let area = Rect { x: 0, y: 0, width: 100, height: 1000 };
+        assert_eq!(layout.arrange(area, &[1, 2, 3], &params(1)), vec![
+            Rect { x: 0, y: 0, width: 100, height: 600 },
+            Rect { x: 0, y: 600, width: 50, height: 400 },
+            Rect { x: 50, y: 600, width: 50, height: 400 },
+        ]);
+    }
+    #[test]
+    fn grid_shares_the_incomplete_last_row() {
+        assert_eq!(Grid.arrange(AREA, &[1, 2, 3, 4, 5], &params(1)), vec![
+            Rect { x: 10, y: 20, width: 333, height: 50 },
+            Rect { x: 343, y: 20, width: 333, height: 50 },
+            Rect { x: 676, y: 20, width: 334, height: 50 },
+            Rect { x: 10, y: 70, width: 500, height: 50 },
+            Rect { x: 510, y: 70, width: 500, height: 50 },
+        ]);
+    }
+
+    #[test]
+    fn grid_of_a_square_number_of_clients_is_square() {
+        let area = Rect { x: 0, y: 0, width: 100, height: 100 };
+        let rects = Grid.arrange(area, &[1, 2, 3, 4], &params(1));
+        assert!(rects.iter().all(|rect| rect.width == 50 && rect.height == 50));
+        assert_eq!(Grid.arrange(area, &[], &params(1)), vec![]);
+    }
+
+    #[test]
+    fn neighbor_prefers_facing_windows() {
+        let from = Rect { x: 0, y: 0, width: 100, height: 100 };
+        let candidates = [
+            (1, from),
+            // Closer, but diagonally.
+            (2, Rect { x: 110, y: 110, width: 100, height: 100 }),
+            // Farther, but overlapping across the direction.
+            (3, Rect { x: 400, y: 80, width: 100, height: 100 }),
+        ];
+        assert_eq!(neighbor(1, from, Direction::Right, &candidates), Some(3));
+    }
+
+    #[test]
+    fn neighbor_is_none_without_window_in_the_direction() {
+        let from = Rect { x: 0, y: 0, width: 100, height: 100 };
+        let candidates = [(1, from), (2, Rect { x: 200, y: 0, width: 100, height: 100 })];
+        assert_eq!(neighbor(1, from, Direction::Left, &candidates), None);
+        assert_eq!(neighbor(1, from, Direction::Up, &candidates), None);
+        assert_eq!(neighbor(1, from, Direction::Right, &candidates), Some(2));
+    }
+}
@@ -1,11 +1,42 @@
+mod action;
+mod client;
+mod config;
+mod ewmh;
+mod hints;
+mod layout;
+mod monitor;
+mod presentation;
+mod signal;
+mod spawn;
+mod workspace;
+
 use x11::xlib;
-use x11::keysym;
 use std::os::raw;
-use std::collections::hash_map;
+use action::Action;
+use client::{Client, ClientModel};
+use config::{ButtonAction, Config, FocusModel, WorkspaceMode};
+use ewmh::{Atoms, Strut, WindowType};
+use hints::{SizeHints, WmHints};
+use layout::{Layout, Rect};
+use monitor::{Monitor, Randr};
+use presentation::Presentation;
+use workspace::Workspace;
 
 #[derive(Default)]
 pub struct Position { x:i32, y:i32 }
 
+/// Focus cycling in progress, see focus_next().
+struct FocusCycle {
+    /// Workspace whose clients are cycled through.
+    workspace: usize,
+    /// Clients cycled through, most recently focused first.
+    windows: Vec<xlib::Window>,
+    /// Modifiers whose release ends the cycle: the configured modifier if
+    /// the binding uses it, else every modifier of the binding. 0 until the
+    /// binding is known.
+    modifiers: u32,
+}
+
 static X_REQUEST_CODE_NAMES : [&str;121] = [
       "",
       "CreateWindow",
@@ -133,42 +164,92 @@ static X_REQUEST_CODE_NAMES : [&str;121] = [
 pub struct WindowManager {
     display: *mut xlib::Display,
     root: raw::c_ulong,
-    clients: hash_map::HashMap<xlib::Window, xlib::Window>,
-    clients_vec: Vec<xlib::Window>,
+    config: Config,
+    clients: ClientModel,
+    layouts: Vec<Box<dyn Layout>>,
+    workspaces: Vec<Workspace>,
+    current_workspace: usize,
+    previous_workspace: usize,
     drag_start_pos: Position,
     drag_start_frame_pos: Position,
     drag_start_frame_size: Position,
+    drag_action: Option<ButtonAction>,
+    focused: Option<xlib::Window>,
+    cycle: Option<FocusCycle>,
+    presentation: Presentation,
+    numlock_mask: u32,
+    scrolllock_mask: u32,
     wm_protocols: xlib::Atom,
     wm_delete_window: xlib::Atom,
+    wm_take_focus: xlib::Atom,
+    atoms: Atoms,
+    /// Monitors, from left to right. There is at least one.
+    monitors: Vec<Monitor>,
+    /// Monitor of the focused client, where new clients go. In per-monitor
+    /// mode, the current workspace is the one it shows.
+    current_monitor: usize,
+    randr: Option<Randr>,
+    /// Unframed panels, with their geometry when they were mapped.
+    docks: Vec<(xlib::Window, Rect)>,
+    /// Screen edges reserved by docks and clients.
+    struts: Vec<(xlib::Window, Strut)>,
+    /// Unframed windows drawing the desktop.
+    desktop_windows: Vec<xlib::Window>,
+    /// Child of the root window proving that an EWMH compliant window manager
+    /// is running.
+    check_window: xlib::Window,
 }
 
 fn in_list(ptr:*mut xlib::Atom, size: i32, elt: xlib::Atom) -> bool {
     for i in 0..size {
         if unsafe { *ptr.offset(i as isize) } == elt { return true }
     }
-    return false
+    false
 }
 
 impl WindowManager {
 
-    fn create() -> WindowManager {
+    fn create(config: Config) -> WindowManager {
         let nullptr : *const std::os::raw::c_char = std::ptr::null();
         unsafe {
             let display = xlib::XOpenDisplay(nullptr);
             let root = xlib::XDefaultRootWindow(display);
             let wm_protocols_cstring : std::ffi::CString = std::ffi::CString::new("WM_PROTOCOLS").expect("CString::new() failed");
             let wm_delete_window_cstring : std::ffi::CString = std::ffi::CString::new("WM_DELETE_WINDOW").expect("CString::new() failed");
-            return WindowManager { 
-                display: display,
-                root: root,
-                clients: hash_map::HashMap::new(),
-                clients_vec: Vec::new(),
+            let wm_take_focus_cstring : std::ffi::CString = std::ffi::CString::new("WM_TAKE_FOCUS").expect("CString::new() failed");
+            let mut wm = WindowManager {
+                display,
+                root,
+                config,
+                clients: Default::default(),
+                layouts: layout::all(),
+                workspaces: Vec::new(),
+                current_workspace: 0,
+                previous_workspace: 0,
                 drag_start_pos: Default::default(),
                 drag_start_frame_pos: Default::default(),
                 drag_start_frame_size: Default::default(),
+                drag_action: None,
+                focused: None,
+                cycle: None,
+                presentation: Default::default(),
+                numlock_mask: 0,
+                scrolllock_mask: 0,
                 wm_protocols: xlib::XInternAtom(display, wm_protocols_cstring.as_ptr(), 0),
                 wm_delete_window: xlib::XInternAtom(display, wm_delete_window_cstring.as_ptr(), 0),
-            }
+                wm_take_focus: xlib::XInternAtom(display, wm_take_focus_cstring.as_ptr(), 0),
+                atoms: Atoms::intern(display),
+                check_window: 0,
+                monitors: Vec::new(),
+                current_monitor: 0,
+                randr: None,
+                docks: Vec::new(),
+                struts: Vec::new(),
+                desktop_windows: Vec::new(),
+            };
+            wm.monitors = monitor::query(display, root, None, wm.screen());
+            wm.resize_workspaces();
+            wm
         }
     }
 
@@ -188,22 +269,17 @@ impl WindowManager {
             std::mem::size_of::<[i8;1024]>() as i32);
         eprintln!("Received X error:\nRequest: {} - {}\nError code: {} - {}\nResource ID: {}",
                   (*e).request_code,
-                  X_REQUEST_CODE_NAMES[(*e).request_code as usize],
+                  X_REQUEST_CODE_NAMES.get((*e).request_code as usize).copied().unwrap_or("<extension request>"),
                   (*e).error_code,
                   std::ffi::CStr::from_ptr(buffer_ptr).to_str().unwrap(),
                   (*e).resourceid);
-        return 1
+        1
     }
 
 
     fn frame(&mut self, w: xlib::Window, was_created_before_window_manager: bool) {
-        // Visual properties of the frame to create.
-        let border_width : u32 = 3;
-        let border_color : u64 = 0xff0000;
-        let bg_color : u64 = 0x0000ff;
-
         // We shouldn't be framing windows we've already framed.
-        assert!(!self.clients.contains_key(&w));
+        assert!(!self.clients.contains(w));
 
         // 1. Retrieve attributes of window to frame.
         let mut x_window_attrs = xlib::XWindowAttributes {
@@ -239,13 +315,50 @@ impl WindowManager {
 
         // 2. If window was created before window manager started, we should frame
         // it only if it is visible and doesn't set override_redirect.
-        if was_created_before_window_manager {
-            if x_window_attrs.override_redirect > 0 || x_window_attrs.map_state != xlib::IsViewable {
+        if was_created_before_window_manager
+            && (x_window_attrs.override_redirect > 0 || x_window_attrs.map_state != xlib::IsViewable) {
                 return;
-            }
         }
 
-        // 3. Create frame.
+        // 3. Apply the policy of the window type. Some windows are not framed.
+        let geometry = Rect {
+            x: x_window_attrs.x,
+            y: x_window_attrs.y,
+            width: x_window_attrs.width as u32,
+            height: x_window_attrs.height as u32,
+        };
+        let window_type = ewmh::window_type(self.display, w, &self.atoms);
+        let wm_hints = WmHints::read(self.display, w);
+        match window_type {
+            WindowType::Dock => {
+                eprintln!("Dock window {}", w);
+                self.docks.push((w, geometry));
+                // Docks without strut reserve the edge they are on.
+                let strut = Strut::read(self.display, w, &self.atoms, self.screen())
+                    .unwrap_or_else(|| Strut::from_geometry(geometry, self.screen()));
+                self.struts.push((w, strut));
+                unsafe { xlib::XSelectInput(self.display, w, xlib::PropertyChangeMask) };
+                self.restack();
+                self.update_workarea();
+                self.refit_placed_clients();
+                self.arrange();
+                return;
+            },
+            WindowType::Desktop => {
+                eprintln!("Desktop window {}", w);
+                self.desktop_windows.push(w);
+                unsafe { xlib::XLowerWindow(self.display, w) };
+                return;
+            },
+            WindowType::Notification => {
+                eprintln!("Notification window {}", w);
+                unsafe { xlib::XRaiseWindow(self.display, w) };
+                return;
+            },
+            WindowType::Dialog | WindowType::Normal => {},
+        }
+
+        // 4. Create frame.
         unsafe {
             let frame = xlib::XCreateSimpleWindow(
                 self.display,
@@ -254,79 +367,489 @@ impl WindowManager {
                 x_window_attrs.y,
                 x_window_attrs.width as u32,
                 x_window_attrs.height as u32,
-                border_width,
-                border_color,
-                bg_color);
-            // 4. Select events on frame.
-            xlib::XSelectInput( self.display, frame, xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask);
-            // 5. Add client to save set, so that it will be restored and kept alive if we
+                self.config.border_width,
+                self.config.border_color,
+                self.config.bg_color);
+            // 5. Select events on frame.
+            xlib::XSelectInput( self.display, frame, xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask | xlib::EnterWindowMask);
+            // 6. Add client to save set, so that it will be restored and kept alive if we
             // crash.
             xlib::XAddToSaveSet(self.display, w);
-            // 6. Reparent client window.
+            // 7. Watch changes of the properties of the client window, and
+            // reparent it.
+            xlib::XSelectInput(self.display, w, xlib::PropertyChangeMask);
             xlib::XReparentWindow( self.display, w, frame, 0, 0);  // Offset of client window within frame.
-            // 7. Map frame.
-            xlib::XMapWindow(self.display, frame);
-            // 8. Save frame handle.
-            self.clients.insert(w,frame);
-            self.clients_vec.push(w);
-            // 9. Grab universal window management actions on client window.
-            //   a. Move windows with ctrl + left button.
-            xlib::XGrabButton(
-                self.display,
-                xlib::Button1,
-                xlib::ControlMask,
-                w,
-                0,
-                (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::ButtonMotionMask) as u32,
-                xlib::GrabModeAsync,
-                xlib::GrabModeAsync,
-                0,
-                0);
-            //   b. Resize windows with ctrl + right button.
-            xlib::XGrabButton(
-                self.display,
-                xlib::Button3,
-                xlib::ControlMask,
-                w,
-                0,
-                (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::ButtonMotionMask) as u32,
-                xlib::GrabModeAsync,
-                xlib::GrabModeAsync,
-                0,
-                0);
-            //   c. Kill windows with ctrl + f4.
-            xlib::XGrabKey(
-                self.display,
-                xlib::XKeysymToKeycode(self.display, x11::keysym::XK_F4 as u64) as i32,
-                xlib::ControlMask,
-                w,
-                0,
-                xlib::GrabModeAsync,
-                xlib::GrabModeAsync);
-            //   d. Switch windows with ctrl + tab.
-            xlib::XGrabKey(
-                self.display,
-                xlib::XKeysymToKeycode(self.display, x11::keysym::XK_Tab as u64) as i32,
-                xlib::ControlMask,
-                w,
-                0,
-                xlib::GrabModeAsync,
-                xlib::GrabModeAsync);
+            // 8. Choose the workspace and monitor of the client. A transient
+            // goes with its parent. Other new clients go to the current
+            // monitor, keeping their position relative to the monitor they
+            // asked for, while clients managed before stay where they are. In
+            // per-monitor mode, clients of a visible workspace go to its
+            // monitor.
+            let transient_for = self.transient_for(w);
+            let workspace = match transient_for {
+                Some(parent) => self.clients[parent].workspace,
+                None => self.initial_workspace(w),
+            };
+            let monitor = match transient_for {
+                Some(parent) => self.clients[parent].monitor,
+                None if was_created_before_window_manager => self.monitor_at(geometry),
+                None => self.current_monitor,
+            };
+            let monitor = match self.config.workspace_mode {
+                WorkspaceMode::PerMonitor => self.monitor_of_workspace(workspace).unwrap_or(monitor),
+                WorkspaceMode::Global => monitor,
+            };
+            let from = self.monitors[self.monitor_at(geometry)].geometry;
+            let to = self.monitors[monitor].geometry;
+            let geometry = Rect { x: geometry.x + to.x - from.x, y: geometry.y + to.y - from.y, ..geometry };
+            // 9. Place the frame, and map it if the client goes to a visible
+            // workspace. Dialogs float in the middle of the screen, or of their
+            // parent. Other clients are kept out of the space of the docks.
+            let floating = window_type == WindowType::Dialog || transient_for.is_some();
+            let geometry = match transient_for {
+                Some(parent) => self.centered_over(geometry, self.clients[parent].geometry),
+                None if floating => self.centered(geometry, monitor),
+                None => self.within_work_area(geometry, monitor),
+            };
+            xlib::XMoveWindow(self.display, frame, geometry.x, geometry.y);
+            if self.is_visible(workspace) {
+                xlib::XMapWindow(self.display, frame);
+            }
+            // 10. Save frame handle.
+            let (instance, class) = self.class_hint(w);
+            let client = Client {
+                title: self.title(w),
+                instance,
+                class,
+                size_hints: SizeHints::read(self.display, w),
+                accepts_input: wm_hints.input,
+                transient_for,
+                floating,
+                ..Client::new(w, frame, geometry, workspace, monitor)
+            };
+            eprintln!("Framed window {} [{}] \"{}\"",w,frame,client.title);
+            self.clients.insert(client);
+        }
+        self.update_client_list();
+        self.update_client_desktop(w);
+        if let Some(strut) = Strut::read(self.display, w, &self.atoms, self.screen()) {
+            self.struts.push((w, strut));
+            self.update_workarea();
+            self.refit_placed_clients();
+        }
+        // Restore the states of a client managed before, for instance by a
+        // previous instance of the window manager.
+        for state in ewmh::get_atoms(self.display, w, self.atoms.net_wm_state) {
+            self.set_state(w, state, true);
+        }
+        if wm_hints.urgent {
+            self.set_state(w, self.atoms.net_wm_state_demands_attention, true);
+        }
+        if wm_hints.iconic {
+            self.set_state(w, self.atoms.net_wm_state_hidden, true);
+        }
+        // 11. Grab universal window management actions on client window. Key
+        // bindings are grabbed once on the root window.
+        self.grab_buttons(w);
+        // 12. Make room for the new client.
+        self.arrange();
+        // 13. Apply presentation rules.
+        let client = &self.clients[w];
+        if self.config.presentation_classes.iter().any(|c| *c == client.instance || *c == client.class) {
+            self.presentation.triggers.push(w);
+            self.set_presentation(true, true);
+        }
+    }
 
-            eprintln!("Framed window {} [{}]",w,frame);
+    /// Workspace of a new client window `w`: the one it asks for with
+    /// _NET_WM_DESKTOP, for instance when the window manager is restarted, or
+    /// the current one.
+    fn initial_workspace(&self, w: xlib::Window) -> usize {
+        match ewmh::get_cardinals(self.display, w, self.atoms.net_wm_desktop).first() {
+            Some(&n) if (n as usize) < self.workspaces.len() => n as usize,
+            _ => self.current_workspace,
         }
+    }
 
+    /// Managed client window that window `w` is a transient of, from its
+    /// WM_TRANSIENT_FOR property.
+    fn transient_for(&self, w: xlib::Window) -> Option<xlib::Window> {
+        let mut parent: xlib::Window = 0;
+        if unsafe { xlib::XGetTransientForHint(self.display, w, &mut parent) } == 0 {
+            return None;
+        }
+        self.clients.resolve(parent).filter(|&parent| parent != w)
+    }
+
+    /// Title of window `w`, from its WM_NAME property.
+    fn title(&self, w: xlib::Window) -> String {
+        let mut name: *mut raw::c_char = std::ptr::null_mut();
+        unsafe {
+            if xlib::XFetchName(self.display, w, &mut name) == 0 || name.is_null() {
+                return String::new();
+            }
+            let title = std::ffi::CStr::from_ptr(name).to_string_lossy().into_owned();
+            xlib::XFree(name as *mut std::ffi::c_void);
+            title
+        }
+    }
+
+    /// Instance and class names of window `w`, from its WM_CLASS property.
+    fn class_hint(&self, w: xlib::Window) -> (String, String) {
+        let mut hint = xlib::XClassHint { res_name: std::ptr::null_mut(), res_class: std::ptr::null_mut() };
+        unsafe {
+            if xlib::XGetClassHint(self.display, w, &mut hint) == 0 {
+                return (String::new(), String::new());
+            }
+            let instance = std::ffi::CStr::from_ptr(hint.res_name).to_string_lossy().into_owned();
+            let class = std::ffi::CStr::from_ptr(hint.res_class).to_string_lossy().into_owned();
+            xlib::XFree(hint.res_name as *mut std::ffi::c_void);
+            xlib::XFree(hint.res_class as *mut std::ffi::c_void);
+            (instance, class)
+        }
+    }
+
+    /// Turn presentation mode on or off. `automatic` tells whether this is done
+    /// by a presentation rule.
+    fn set_presentation(&mut self, active: bool, automatic: bool) {
+        if self.presentation.active == active {
+            return;
+        }
+        self.presentation.active = active;
+        self.presentation.automatic = active && automatic;
+        unsafe {
+            if active {
+                // Inhibit screen blanking, remembering the current settings.
+                let (mut timeout, mut interval, mut prefer_blanking, mut allow_exposures) = (0, 0, 0, 0);
+                xlib::XGetScreenSaver(self.display, &mut timeout, &mut interval, &mut prefer_blanking, &mut allow_exposures);
+                xlib::XSetScreenSaver(self.display, 0, interval, prefer_blanking, allow_exposures);
+                let (mut event_base, mut error_base) = (0, 0);
+                let (mut power_level, mut dpms_state) = (0, 0);
+                let dpms_enabled = x11::dpms::DPMSQueryExtension(self.display, &mut event_base, &mut error_base) != 0
+                    && x11::dpms::DPMSInfo(self.display, &mut power_level, &mut dpms_state) != 0
+                    && dpms_state != 0;
+                if dpms_enabled {
+                    x11::dpms::DPMSDisable(self.display);
+                }
+                self.presentation.saved_screen_saver = Some((timeout, interval, prefer_blanking, allow_exposures, dpms_enabled));
+            } else if let Some((timeout, interval, prefer_blanking, allow_exposures, dpms_enabled)) = self.presentation.saved_screen_saver.take() {
+                xlib::XSetScreenSaver(self.display, timeout, interval, prefer_blanking, allow_exposures);
+                if dpms_enabled {
+                    x11::dpms::DPMSEnable(self.display);
+                }
+            }
+        }
+        for client in self.clients.iter() {
+            self.update_border(client.window);
+        }
+        eprintln!("Presentation mode {}", if active { "on" } else { "off" });
+    }
+
+    /// Find which modifiers NumLock and ScrollLock are mapped to. Bindings must
+    /// work whatever the state of these locks and of CapsLock.
+    fn update_lock_masks(&mut self) {
+        self.numlock_mask = 0;
+        self.scrolllock_mask = 0;
+        unsafe {
+            let numlock = xlib::XKeysymToKeycode(self.display, x11::keysym::XK_Num_Lock as u64);
+            let scrolllock = xlib::XKeysymToKeycode(self.display, x11::keysym::XK_Scroll_Lock as u64);
+            let modmap = xlib::XGetModifierMapping(self.display);
+            let max_keypermod = (*modmap).max_keypermod as usize;
+            for modifier in 0..8 {
+                for k in 0..max_keypermod {
+                    let keycode = *(*modmap).modifiermap.add(modifier * max_keypermod + k);
+                    if keycode == 0 {
+                        continue;
+                    }
+                    if keycode == numlock {
+                        self.numlock_mask = 1 << modifier;
+                    }
+                    if keycode == scrolllock {
+                        self.scrolllock_mask = 1 << modifier;
+                    }
+                }
+            }
+            xlib::XFreeModifiermap(modmap);
+        }
+    }
+
+    /// Modifier mask of the modifier key `keycode`, or 0 for other keys.
+    fn modifier_mask(&self, keycode: u32) -> u32 {
+        let mut mask = 0;
+        unsafe {
+            let modmap = xlib::XGetModifierMapping(self.display);
+            let max_keypermod = (*modmap).max_keypermod as usize;
+            for modifier in 0..8 {
+                for k in 0..max_keypermod {
+                    let code = *(*modmap).modifiermap.add(modifier * max_keypermod + k);
+                    if code != 0 && code as u32 == keycode {
+                        mask |= 1 << modifier;
+                    }
+                }
+            }
+            xlib::XFreeModifiermap(modmap);
+        }
+        mask
+    }
+
+    /// Whether a key of modifiers `mask` is held down.
+    fn modifiers_down(&self, mask: u32) -> bool {
+        let mut keys: [raw::c_char; 32] = [0; 32];
+        let mut down = false;
+        unsafe {
+            xlib::XQueryKeymap(self.display, keys.as_mut_ptr());
+            let modmap = xlib::XGetModifierMapping(self.display);
+            let max_keypermod = (*modmap).max_keypermod as usize;
+            for modifier in (0..8).filter(|&modifier| mask & (1 << modifier) != 0) {
+                for k in 0..max_keypermod {
+                    let keycode = *(*modmap).modifiermap.add(modifier * max_keypermod + k) as usize;
+                    if keycode != 0 && keys[keycode / 8] as u8 & (1 << (keycode % 8)) != 0 {
+                        down = true;
+                    }
+                }
+            }
+            xlib::XFreeModifiermap(modmap);
+        }
+        down
+    }
+
+    /// Every combination of the lock modifiers, to be added to the modifiers of
+    /// a grab.
+    fn lock_combinations(&self) -> Vec<u32> {
+        let locks = [xlib::LockMask, self.numlock_mask, self.scrolllock_mask];
+        let mut combinations = vec![0];
+        for &lock in locks.iter().filter(|&&lock| lock != 0) {
+            let with_lock: Vec<u32> = combinations.iter().map(|&m| m | lock).collect();
+            combinations.extend(with_lock);
+        }
+        combinations
+    }
+
+    /// Modifiers of an event, ignoring the lock modifiers.
+    fn clean_mask(&self, state: u32) -> u32 {
+        state & config::MODIFIER_MASK & !(xlib::LockMask | self.numlock_mask | self.scrolllock_mask)
+    }
+
+    /// Grab mouse bindings (move, resize, ...) on client window `w`, and any
+    /// click if it is not focused, so that the click focuses it.
+    fn grab_buttons(&self, w: xlib::Window) {
+        // The click is only held until the client is focused, then it is
+        // replayed to the client (see on_button_press()).
+        self.ungrab_buttons(w);
+        if self.focused != Some(w) {
+            unsafe {
+                xlib::XGrabButton(
+                    self.display,
+                    xlib::AnyButton as u32,
+                    xlib::AnyModifier,
+                    w,
+                    0,
+                    xlib::ButtonPressMask as u32,
+                    xlib::GrabModeSync,
+                    xlib::GrabModeAsync,
+                    0,
+                    0);
+            }
+        }
+        for binding in &self.config.buttons {
+            for locks in self.lock_combinations() {
+                unsafe {
+                    xlib::XGrabButton(
+                        self.display,
+                        binding.button,
+                        binding.modifiers | locks,
+                        w,
+                        0,
+                        (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::ButtonMotionMask) as u32,
+                        xlib::GrabModeAsync,
+                        xlib::GrabModeAsync,
+                        0,
+                        0);
+                }
+            }
+        }
+    }
+
+    fn ungrab_buttons(&self, w: xlib::Window) {
+        unsafe { xlib::XUngrabButton(self.display, xlib::AnyButton as u32, xlib::AnyModifier, w) };
+    }
+
+    /// Grab key bindings (close, switch windows, ...) on the root window, so
+    /// that they work whichever window has the focus, if any.
+    fn grab_keys(&self) {
+        unsafe {
+            xlib::XUngrabKey(self.display, xlib::AnyKey, xlib::AnyModifier, self.root);
+            for binding in &self.config.keys {
+                let keycode = xlib::XKeysymToKeycode(self.display, binding.keysym) as i32;
+                if keycode == 0 {
+                    eprintln!("No key code for key sym {}", binding.keysym);
+                    continue;
+                }
+                for locks in self.lock_combinations() {
+                    xlib::XGrabKey(
+                        self.display,
+                        keycode,
+                        binding.modifiers | locks,
+                        self.root,
+                        1,
+                        xlib::GrabModeAsync,
+                        xlib::GrabModeAsync);
+                }
+            }
+        }
+    }
+
+    fn reload(&mut self) {
+        // 1. Read the new configuration. On error we keep the current one.
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("Invalid configuration: {}\nKeeping current configuration.", err);
+                return;
+            }
+        };
+        // 2. Replace the bindings on the root window and on every client.
+        for client in self.clients.iter() {
+            self.ungrab_buttons(client.window);
+        }
+        self.config = config;
+        self.grab_keys();
+        for client in self.clients.iter() {
+            self.grab_buttons(client.window);
+        }
+        // 3. Repaint frames.
+        for client in self.clients.iter() {
+            let border_width = if client.fullscreen { 0 } else { self.config.border_width };
+            unsafe {
+                xlib::XSetWindowBorderWidth(self.display, client.frame, border_width);
+                xlib::XSetWindowBackground(self.display, client.frame, self.config.bg_color);
+                xlib::XClearWindow(self.display, client.frame);
+            }
+        }
+        for client in self.clients.iter() {
+            self.update_border(client.window);
+        }
+        // 4. Adapt workspaces.
+        self.resize_workspaces();
+        self.update_desktops();
+        self.arrange();
+        eprintln!("Reloaded configuration");
+    }
+
+    /// Adapt the workspaces to the configured number of workspaces. Clients on
+    /// removed workspaces are moved to the last remaining one.
+    fn resize_workspaces(&mut self) {
+        let n = self.config.workspaces.len();
+        if n < self.workspaces.len() && self.current_workspace >= n {
+            self.switch_workspace(n - 1);
+        }
+        self.previous_workspace = std::cmp::min(self.previous_workspace, n - 1);
+        if self.workspaces.len() > n {
+            let moved: Vec<xlib::Window> = self.clients.iter()
+                .filter(|client| client.workspace >= n)
+                .map(|client| client.window)
+                .collect();
+            for w in moved {
+                self.clients.get_mut(w).unwrap().workspace = n - 1;
+                self.update_client_desktop(w);
+            }
+            self.workspaces.truncate(n);
+        }
+        let layout = self.layouts.iter().position(|l| l.name() == self.config.layout).unwrap_or(0);
+        let (tiled, params) = (self.config.tiling, self.config.layout_params);
+        self.workspaces.resize_with(n, || Workspace::new(tiled, layout, params));
+        self.assign_workspaces();
+    }
+
+    /// Give every monitor a valid workspace to show, according to the
+    /// workspace mode, and show it. In per-monitor mode monitors keep their
+    /// workspace if they can, and the others show the first hidden ones.
+    fn assign_workspaces(&mut self) {
+        let n = self.workspaces.len();
+        match self.config.workspace_mode {
+            WorkspaceMode::Global => for monitor in &mut self.monitors {
+                monitor.workspace = self.current_workspace;
+            },
+            WorkspaceMode::PerMonitor => {
+                let mut shown: Vec<usize> = Vec::new();
+                for monitor in &mut self.monitors {
+                    if monitor.workspace >= n || shown.contains(&monitor.workspace) {
+                        // With fewer workspaces than monitors, the last ones
+                        // are shown twice.
+                        monitor.workspace = (0..n).find(|i| !shown.contains(i)).unwrap_or(n - 1);
+                    }
+                    shown.push(monitor.workspace);
+                }
+                self.current_workspace = self.monitors[self.current_monitor].workspace;
+            },
+        }
+        ewmh::set_cardinals(self.display, self.root, self.atoms.net_current_desktop, &[self.current_workspace as u64]);
+        self.show_workspaces();
+    }
+
+    /// Whether workspace `n` is shown on some monitor.
+    fn is_visible(&self, n: usize) -> bool {
+        self.monitor_of_workspace(n).is_some()
+    }
+
+    /// First monitor showing workspace `n`, if any.
+    fn monitor_of_workspace(&self, n: usize) -> Option<usize> {
+        self.monitors.iter().position(|m| m.workspace == n)
+    }
+
+    /// Map the frames of the clients of visible workspaces and unmap the
+    /// others. In per-monitor mode, clients also move to the monitor showing
+    /// their workspace.
+    ///
+    /// Only frames are mapped and unmapped. Client windows stay mapped inside
+    /// their frame, so hiding them does not generate an UnmapNotify that would
+    /// make us unframe them. The UnmapNotify of the frames themselves are
+    /// ignored since frames are not clients.
+    fn show_workspaces(&mut self) {
+        let clients: Vec<(xlib::Window, usize, usize)> = self.clients.iter()
+            .map(|client| (client.window, client.workspace, client.monitor))
+            .collect();
+        for (w, workspace, monitor) in clients {
+            match self.monitor_of_workspace(workspace) {
+                Some(to) => {
+                    let here = self.monitors.get(monitor).is_some_and(|m| m.workspace == workspace);
+                    if self.config.workspace_mode == WorkspaceMode::PerMonitor && !here {
+                        self.move_to_monitor(w, to);
+                    }
+                    if !self.clients[w].hidden {
+                        unsafe { xlib::XMapWindow(self.display, self.clients[w].frame) };
+                    }
+                },
+                None => {
+                    unsafe { xlib::XUnmapWindow(self.display, self.clients[w].frame) };
+                },
+            }
+        }
+    }
+
+    /// Make monitor `monitor` the current one. In per-monitor mode, its
+    /// workspace becomes the current workspace.
+    fn set_current_monitor(&mut self, monitor: usize) {
+        self.current_monitor = monitor;
+        let workspace = self.monitors[monitor].workspace;
+        if self.config.workspace_mode == WorkspaceMode::PerMonitor && workspace != self.current_workspace {
+            self.current_workspace = workspace;
+            ewmh::set_cardinals(self.display, self.root, self.atoms.net_current_desktop, &[workspace as u64]);
+        }
     }
 
     fn unframe(&mut self, w: xlib::Window) {
         // We reverse the steps taken in Frame().
-        match self.clients.get(&w){
+        match self.clients.remove(w) {
             None => panic!("unframe"),
-            Some(frame) =>
+            Some(client) =>
             {
+                // 0. Release the click-to-focus grab, should the client window
+                // outlive its frame.
+                self.ungrab_buttons(w);
                 unsafe {
                     // 1. Unmap frame.
-                    xlib::XUnmapWindow(self.display, *frame);
+                    xlib::XUnmapWindow(self.display, client.frame);
                     // 2. Reparent client window.
                     //println!("reparent (1)");
                     //xlib::XReparentWindow( self.display, w, self.root, 0, 0);  // Offset of client window within root.
@@ -335,12 +858,28 @@ impl WindowManager {
                     // 3. Remove client window from save set, as it is now unrelated to us.
                     //xlib::XRemoveFromSaveSet(self.display, w);
                     // 4. Destroy frame.
-                    xlib::XDestroyWindow(self.display, *frame);
+                    xlib::XDestroyWindow(self.display, client.frame);
+                    // 5. The window is withdrawn, it is on no desktop anymore.
+                    xlib::XDeleteProperty(self.display, w, self.atoms.net_wm_desktop);
+                    xlib::XDeleteProperty(self.display, w, self.atoms.net_wm_state);
+                }
+                // 6. Drop reference to frame handle.
+                eprintln!("Unframed window {} [{}]",w,client.frame);
+                self.update_client_list();
+                self.workspaces[client.workspace].forget(w);
+                if let Some(cycle) = &mut self.cycle {
+                    cycle.windows.retain(|&x| x != w);
+                }
+                self.arrange();
+                // 7. Give the focus back to the previously focused client.
+                if self.focused == Some(w) {
+                    self.focus_last();
+                }
+                self.presentation.forget(w);
+                self.forget_strut(w);
+                if self.presentation.automatic && self.presentation.triggers.is_empty() {
+                    self.set_presentation(false, true);
                 }
-                // 5. Drop reference to frame handle.
-                eprintln!("Unframed window {} [{}]",w,frame);
-                self.clients.remove(&w);
-                self.clients_vec.retain(|&x| x != w);
             }
         };
     }
@@ -354,11 +893,23 @@ impl WindowManager {
 
     fn on_map_notify(&self, _: &xlib::XMapEvent) {}
 
-    fn on_unnmap_notify(&mut self, e: &xlib::XUnmapEvent) -> () {
+    fn on_unnmap_notify(&mut self, e: &xlib::XUnmapEvent) {
+        // Unframed windows are simply forgotten.
+        if self.docks.iter().any(|&(w, _)| w == e.window) {
+            eprintln!("Dock window {} went away", e.window);
+            self.docks.retain(|&(w, _)| w != e.window);
+            self.forget_strut(e.window);
+            return;
+        }
+        if self.desktop_windows.contains(&e.window) {
+            self.desktop_windows.retain(|&w| w != e.window);
+            return;
+        }
+
         // If the window is a client window we manage, unframe it upon UnmapNotify. We
         // need the check because we will receive an UnmapNotify event for a frame
         // window we just destroyed ourselves.
-        if !self.clients.contains_key(&e.window) {
+        if !self.clients.contains(e.window) {
             eprintln!("Ignore UnmapNotify for non-client window {}",e.window);
             return;
         }
@@ -380,17 +931,25 @@ impl WindowManager {
         self.unframe(e.window);
     }
 
-    fn on_configure_notify(&self, _: &xlib::XConfigureEvent) -> () { }
+    fn on_configure_notify(&self, _: &xlib::XConfigureEvent) { }
 
-    fn on_map_request(&mut self, e: &xlib::XMapRequestEvent) -> () {
+    fn on_map_request(&mut self, e: &xlib::XMapRequestEvent) {
+        // New windows must not steal the focus during a presentation, except
+        // dialogs of the focused window. Framing may start the presentation.
+        let was_presenting = self.presentation.active;
         // 1. Frame or re-frame window.
         self.frame(e.window, false);
+        let steal_focus = !was_presenting
+            || self.clients.get(e.window).is_some_and(|c| c.transient_for.is_some() && c.transient_for == self.focused);
         // 2. Actually map window.
         unsafe { xlib::XMapWindow(self.display, e.window) };
-        return
+        // 3. Give it the focus, unless it went to a hidden workspace.
+        if steal_focus && self.clients.get(e.window).is_some_and(|c| self.is_visible(c.workspace) && !c.hidden) {
+            self.focus(e.window);
+        }
     }
 
-    fn on_configure_request(&self, e: &xlib::XConfigureRequestEvent) -> () {
+    fn on_configure_request(&mut self, e: &xlib::XConfigureRequestEvent) {
         let mut changes = xlib::XWindowChanges {
             x : e.x,
             y : e.y,
@@ -400,155 +959,1225 @@ impl WindowManager {
             sibling : e.above,
             stack_mode : e.detail
         };
-        match self.clients.get(&e.window) {
-            None => {},
-            Some (frame) =>
-            {
-                unsafe { xlib::XConfigureWindow(self.display, *frame, e.value_mask as u32, &mut changes) };
-                eprintln!("Resize [{}] to ({},{})",frame,e.width,e.height);
+        // Clients are moved with their frame, within their size hints. The
+        // requested position is that of the outer corner of the frame. Clients
+        // placed by the window manager keep their geometry, and are told so
+        // with a synthetic ConfigureNotify as required by ICCCM.
+        if let Some(client) = self.clients.get(e.window) {
+            let w = e.window;
+            let mask = e.value_mask as u32;
+            let tiled = self.workspaces[client.workspace].tiled && client.is_tiled();
+            if tiled || client.fullscreen || client.maximized_vert || client.maximized_horz {
+                eprintln!("Deny configure request of window {}", w);
+                self.send_configure_notify(w);
+            } else {
+                let mut geometry = client.geometry;
+                if mask & xlib::CWX as u32 != 0 { geometry.x = e.x; }
+                if mask & xlib::CWY as u32 != 0 { geometry.y = e.y; }
+                if mask & xlib::CWWidth as u32 != 0 { geometry.width = e.width as u32; }
+                if mask & xlib::CWHeight as u32 != 0 { geometry.height = e.height as u32; }
+                self.move_resize(w, geometry);
+                eprintln!("Resize [{}] to ({},{})",w,geometry.width,geometry.height);
             }
+            // Stacking requests relative to a sibling are not supported.
+            if mask & xlib::CWStackMode as u32 != 0 && mask & xlib::CWSibling as u32 == 0 {
+                match e.detail {
+                    xlib::Above => self.raise(w),
+                    xlib::Below => self.lower(w),
+                    _ => {},
+                }
+            }
+            return;
         }
         unsafe { xlib::XConfigureWindow(self.display, e.window, e.value_mask as u32, &mut changes) };
         eprintln!("Resize [{}] to ({},{})",e.window,e.width,e.height);
     }
 
-    fn on_button_press(&mut self, e: &xlib::XButtonEvent) -> () {
-        match self.clients.get(&e.window){
-            None => panic!("on_button_press"),
-            Some(frame) =>
+    fn on_button_press(&mut self, e: &xlib::XButtonEvent) {
+        match self.clients.resolve(e.window) {
+            // A grab left on a window we no longer manage: let the window have
+            // the click rather than keep the pointer frozen.
+            None => unsafe {
+                xlib::XAllowEvents(self.display, xlib::ReplayPointer, xlib::CurrentTime);
+            },
+            Some(w) =>
             {
+                // 0. Find the binding that triggered the grab. Without one, this
+                // is a click on an unfocused client: focus it and let the
+                // client have the click.
+                self.drag_action = self.config.buttons.iter()
+                    .find(|b| b.button == e.button && b.modifiers == self.clean_mask(e.state))
+                    .map(|b| b.action);
+                if self.drag_action.is_none() {
+                    self.focus_by_pointer(w);
+                    unsafe { xlib::XAllowEvents(self.display, xlib::ReplayPointer, xlib::CurrentTime) };
+                    return;
+                }
+
                 // 1. Save initial cursor position.
                 self.drag_start_pos = Position { x:e.x_root, y:e.y_root };
 
                 // 2. Save initial window info.
-                let mut returned_root : xlib::Window = 0;
-                let mut x = 0;
-                let mut y = 0;
-                let mut width = 0;
-                let mut height = 0;
-                let mut border_width = 0;
-                let mut depth = 0;
-                unsafe {
-                    assert!(xlib::XGetGeometry(
-                            self.display,
-                            *frame,
-                            &mut returned_root,
-                            &mut x, &mut y,
-                            &mut width, &mut height,
-                            &mut border_width,
-                            &mut depth) > 0);
+                let client = &self.clients[w];
+                let geometry = client.geometry;
+                self.drag_start_frame_pos = Position{x: geometry.x, y: geometry.y};
+                self.drag_start_frame_size = Position{x: geometry.width as i32, y: geometry.height as i32};
+
+                // 3. Raise clicked window to top and focus it.
+                self.focus(w);
+
+                // 4. A window placed by hand leaves the layout.
+                let client = &self.clients[w];
+                if self.workspaces[client.workspace].tiled && !client.floating {
+                    self.clients.get_mut(w).unwrap().floating = true;
+                    self.arrange();
                 }
-                self.drag_start_frame_pos = Position{x, y};
-                self.drag_start_frame_size = Position{x:width as i32, y:height as i32};
-
-                // 3. Raise clicked window to top.
-                unsafe { xlib::XRaiseWindow(self.display, *frame); }
-
-            }
-        }
-    }
-
-    fn on_button_release(&self, _: &xlib::XButtonEvent) -> () { }
-
-    
-    fn on_key_press(&self, e: &xlib::XKeyEvent) -> () {
-
-        if (e.state & xlib::ControlMask > 0) &&
-            (e.keycode == unsafe { xlib::XKeysymToKeycode(self.display, keysym::XK_F4 as u64) as u32 }) {
-                // ctrl + f4: Close window.
-                //
-                // There are two ways to tell an X window to close. The first is to send it
-                // a message of type WM_PROTOCOLS and value WM_DELETE_WINDOW. If the client
-                // has not explicitly marked itself as supporting this more civilized
-                // behavior (using XSetWMProtocols()), we kill it with XKillClient().
-                let mut supported_protocols: *mut xlib::Atom = std::ptr::null_mut();
-                let mut num_supported_protocols = 0;
-                if unsafe { xlib::XGetWMProtocols(self.display, e.window, &mut supported_protocols, &mut num_supported_protocols) } > 0
-                    && in_list(supported_protocols, num_supported_protocols, self.wm_delete_window)
-                    {
-                        eprintln!("Gracefully deleting window {}",e.window);
-                        // 1. Construct message.
-                        let mut data = xlib::ClientMessageData::new();
-                        data.set_long(0,self.wm_delete_window as i64);
-                        let mut msg = xlib::XEvent {
-                            client_message: xlib::XClientMessageEvent {
-                                type_: xlib::ClientMessage,
-                                message_type: self.wm_protocols,
-                                window: e.window,
-                                format: 32,
-                                data: data,
-                                send_event: 0,
-                                display: 0 as *mut xlib::Display,
-                                serial: 0,
-                            }
-                        };
-                        // 2. Send message to window to be closed.
-                        unsafe { assert!(xlib::XSendEvent(self.display, e.window, 0, 0, &mut msg) > 0) };
-                    } else {
-                        eprintln!("Killing window {}",e.window);
-                        unsafe { xlib::XKillClient(self.display, e.window) };
-                    }
-            } else if (e.state & xlib::ControlMask) > 0 &&
-                (e.keycode == unsafe { xlib::XKeysymToKeycode(self.display, keysym::XK_Tab as u64) as u32 }) {
-                    // ctrl + tab: Switch window.
-                    // 1. Find next window.
-                    let next =
-                        match self.clients_vec.iter().position(|&x| x == e.window) {
-                            None => { panic!("") }
-                            Some(i) =>
-                             if i+1 < self.clients_vec.len() { self.clients_vec[i+1] }
-                             else { self.clients_vec[0] }
-                            
-                        };
-                    let frame = self.clients.get(&next).unwrap();
-                    unsafe {
-                        xlib::XRaiseWindow(self.display, *frame);
-                        xlib::XSetInputFocus(self.display, next, xlib::RevertToPointerRoot, xlib::CurrentTime);
-                    }
+            }
+        }
+    }
+
+    fn on_enter_notify(&mut self, e: &xlib::XCrossingEvent) {
+        // Only the pointer moving counts, not grabs starting or ending.
+        if self.config.focus_model == FocusModel::Click || e.mode != xlib::NotifyNormal {
+            return;
+        }
+        // 1. The pointer moved to the desktop.
+        if e.window == self.root {
+            if self.config.focus_model == FocusModel::FollowMouse && self.focused.is_some() {
+                unsafe { xlib::XSetInputFocus(self.display, xlib::PointerRoot as u64, xlib::RevertToPointerRoot, xlib::CurrentTime) };
+                self.set_focused(None);
+            }
+            return;
+        }
+        // 2. The pointer moved to a client, unless it only moved from the
+        // client window to its frame.
+        if e.detail == xlib::NotifyInferior {
+            return;
+        }
+        if let Some(w) = self.clients.resolve(e.window).filter(|&w| self.focused != Some(w)) {
+            self.focus_by_pointer(w);
+        }
+    }
+
+    fn on_button_release(&mut self, e: &xlib::XButtonEvent) {
+        // A client dragged onto another monitor makes it the current one.
+        if self.drag_action.take().is_some() {
+            if let Some(w) = self.clients.resolve(e.window) {
+                self.settle_on_monitor(w);
+                self.set_current_monitor(self.clients[w].monitor);
+            }
+        }
+    }
+
+    /// Make client window `w`, moved by hand, belong to the monitor showing
+    /// its center and, in per-monitor mode, to the workspace shown there.
+    fn settle_on_monitor(&mut self, w: xlib::Window) {
+        let client = &self.clients[w];
+        let monitor = self.monitor_at(client.geometry);
+        let shown = self.monitors.get(client.monitor).is_some_and(|m| m.workspace == client.workspace);
+        let workspace = self.monitors[monitor].workspace;
+        if self.config.workspace_mode == WorkspaceMode::PerMonitor && shown && workspace != client.workspace {
+            self.workspaces[client.workspace].forget(w);
+            self.clients.get_mut(w).unwrap().workspace = workspace;
+            self.update_client_desktop(w);
+        }
+        self.clients.get_mut(w).unwrap().monitor = monitor;
+    }
+
+    fn on_key_press(&mut self, e: &xlib::XKeyEvent) {
+        let state = self.clean_mask(e.state);
+        let action = self.config.keys.iter()
+            .find(|b| b.modifiers == state
+                  && e.keycode == unsafe { xlib::XKeysymToKeycode(self.display, b.keysym) as u32 })
+            .map(|b| b.action.clone());
+
+        match action {
+            None => eprintln!("No binding for key {} with state {}", e.keycode, e.state),
+            Some(action) => self.dispatch(&action),
+        }
+        // Focus cycling lasts until the modifier is released, so that Shift
+        // can be pressed and released to change the direction meanwhile. The
+        // keyboard is held, so that we are told when.
+        let modifiers = match state & self.config.modifier {
+            0 => state,
+            modifier => modifier,
+        };
+        if let Some(cycle) = self.cycle.as_mut().filter(|cycle| cycle.modifiers == 0) {
+            cycle.modifiers = modifiers;
+            let grabbed = modifiers != 0 && unsafe {
+                xlib::XGrabKeyboard(self.display, self.root, 0, xlib::GrabModeAsync, xlib::GrabModeAsync, xlib::CurrentTime)
+            } == xlib::GrabSuccess;
+            if !grabbed {
+                cycle.modifiers = 0;
+                self.end_cycle();
+            } else if !self.modifiers_down(modifiers) {
+                // The modifiers were released before the grab, and the client
+                // got the KeyRelease events.
+                self.end_cycle();
+            }
+        }
+    }
+
+    /// Execute `action`. Actions on a window apply to the focused client, and
+    /// do nothing if there is none.
+    fn dispatch(&mut self, action: &Action) {
+        eprintln!("Dispatching {:?} on window {:?}", action, self.focused);
+        let focused = self.focused.filter(|&w| self.clients.contains(w));
+        match action {
+            Action::Close => if let Some(w) = focused {
+                self.close(w);
+            },
+            Action::Kill => if let Some(w) = focused {
+                eprintln!("Killing window {}",w);
+                unsafe { xlib::XKillClient(self.display, w) };
+            },
+            Action::FocusDirection(direction) => if let Some(w) = focused {
+                // Any visible client, whatever its monitor.
+                let candidates: Vec<(xlib::Window, Rect)> = self.clients.iter()
+                    .filter(|client| self.is_visible(client.workspace) && !client.hidden)
+                    .filter(|client| self.presentation.can_cycle_to(client.window))
+                    .map(|client| (client.window, client.geometry))
+                    .collect();
+                if let Some(x) = layout::neighbor(w, self.clients[w].geometry, *direction, &candidates) {
+                    self.focus(x);
                 }
+            },
+            Action::SwapDirection(direction) => if let Some(w) = focused {
+                // Only clients sharing the layout of the focused one.
+                let (workspace, monitor) = (self.clients[w].workspace, self.clients[w].monitor);
+                let candidates: Vec<(xlib::Window, Rect)> = self.clients.on_workspace(workspace)
+                    .filter(|client| client.monitor == monitor && !client.hidden)
+                    .map(|client| (client.window, client.geometry))
+                    .collect();
+                if let Some(x) = layout::neighbor(w, self.clients[w].geometry, *direction, &candidates) {
+                    self.swap(w, x);
+                }
+            },
+            Action::FocusNext => self.focus_next(true),
+            Action::FocusPrev => self.focus_next(false),
+            Action::Raise => if let Some(w) = focused {
+                self.raise(w);
+            },
+            Action::Lower => if let Some(w) = focused {
+                self.lower(w);
+            },
+            Action::Move(dx, dy) => if let Some(w) = focused {
+                let geometry = self.clients[w].geometry;
+                self.move_resize(w, Rect { x: geometry.x + dx, y: geometry.y + dy, ..geometry });
+            },
+            Action::Resize(dw, dh) => if let Some(w) = focused {
+                // Window dimensions must stay positive.
+                let geometry = self.clients[w].geometry;
+                let width = std::cmp::max(geometry.width as i32 + dw, 1) as u32;
+                let height = std::cmp::max(geometry.height as i32 + dh, 1) as u32;
+                self.move_resize(w, Rect { width, height, ..geometry });
+            },
+            Action::ToggleFullscreen => if let Some(w) = focused {
+                let fullscreen = self.clients[w].fullscreen;
+                self.set_state(w, self.atoms.net_wm_state_fullscreen, !fullscreen);
+            },
+            Action::ToggleFloating => if let Some(w) = focused {
+                let client = self.clients.get_mut(w).unwrap();
+                client.floating = !client.floating;
+                self.arrange();
+            },
+            Action::Reload => self.reload(),
+            Action::Spawn(argv) => {
+                let display_name = unsafe { std::ffi::CStr::from_ptr(xlib::XDisplayString(self.display)) };
+                spawn::spawn(argv, &display_name.to_string_lossy());
+            },
+            Action::SwitchWorkspace(n) => self.switch_workspace(*n),
+            Action::MoveToWorkspace(n) => if let Some(w) = focused {
+                self.move_to_workspace(w, *n);
+            },
+            Action::LastWorkspace => self.switch_workspace(self.previous_workspace),
+            Action::ToggleTiling => {
+                let workspace = &mut self.workspaces[self.current_workspace];
+                workspace.tiled = !workspace.tiled;
+                self.arrange();
+            },
+            Action::IncMaster(n) => {
+                let params = &mut self.workspaces[self.current_workspace].layout_params;
+                params.master_count = std::cmp::max(params.master_count as i32 + n, 0) as usize;
+                self.arrange();
+            },
+            Action::AdjustMasterRatio(delta) => {
+                let params = &mut self.workspaces[self.current_workspace].layout_params;
+                params.master_ratio = (params.master_ratio + delta).clamp(0.05, 0.95);
+                self.arrange();
+            },
+            Action::CycleLayout => {
+                let workspace = &mut self.workspaces[self.current_workspace];
+                workspace.layout = (workspace.layout + 1) % self.layouts.len();
+                workspace.tiled = true;
+                eprintln!("Layout {}", self.layouts[workspace.layout].name());
+                self.arrange();
+            },
+            Action::FocusMonitor(n) => {
+                let count = self.monitors.len() as i32;
+                let monitor = (self.current_monitor as i32 + n).rem_euclid(count) as usize;
+                self.focus_monitor(monitor);
+            },
+            Action::MoveToMonitor(n) => if let Some(w) = focused {
+                let count = self.monitors.len() as i32;
+                let monitor = (self.clients[w].monitor as i32 + n).rem_euclid(count) as usize;
+                // In per-monitor mode, the client also goes to the workspace
+                // of the monitor.
+                self.move_to_workspace(w, self.monitors[monitor].workspace);
+                self.move_to_monitor(w, monitor);
+                self.arrange();
+                self.focus(w);
+            },
+            Action::TogglePresentation => self.set_presentation(!self.presentation.active, false),
+            Action::ToggleShared => if let Some(w) = focused {
+                if self.presentation.shared.contains(&w) {
+                    self.presentation.shared.retain(|&x| x != w);
+                } else {
+                    self.presentation.shared.push(w);
+                }
+                self.update_border(w);
+            },
+            Action::SetLayout(name) => {
+                if let Some(layout) = self.layouts.iter().position(|l| l.name() == name) {
+                    let workspace = &mut self.workspaces[self.current_workspace];
+                    workspace.layout = layout;
+                    workspace.tiled = true;
+                    self.arrange();
+                }
+            },
+        }
     }
 
-    fn on_key_release(&self, _: &xlib::XKeyEvent) -> () { }
+    /// The whole screen.
+    fn screen(&self) -> Rect {
+        unsafe {
+            let screen = xlib::XDefaultScreen(self.display);
+            Rect {
+                x: 0,
+                y: 0,
+                width: xlib::XDisplayWidth(self.display, screen) as u32,
+                height: xlib::XDisplayHeight(self.display, screen) as u32,
+            }
+        }
+    }
+
+    /// Area of the screen available for clients, that is the whole screen
+    /// but the edges reserved by struts.
+    fn work_area(&self) -> Rect {
+        let struts: Vec<Strut> = self.struts.iter().map(|&(_, strut)| strut).collect();
+        ewmh::work_area(self.screen(), self.screen(), &struts)
+    }
 
-    fn on_motion_notify(&self, e: &xlib::XMotionEvent) -> () {
-        match self.clients.get(&e.window){
-            None => panic!("on_motion_notify"),
-            Some(frame) =>
+    /// Part of monitor `monitor` available for clients.
+    fn monitor_work_area(&self, monitor: usize) -> Rect {
+        let struts: Vec<Strut> = self.struts.iter().map(|&(_, strut)| strut).collect();
+        ewmh::work_area(self.monitors[monitor].geometry, self.screen(), &struts)
+    }
+
+    /// Index of the monitor showing the center of `geometry`, or else the
+    /// current monitor.
+    fn monitor_at(&self, geometry: Rect) -> usize {
+        let x = geometry.x + geometry.width as i32 / 2;
+        let y = geometry.y + geometry.height as i32 / 2;
+        self.monitors.iter()
+            .position(|m| {
+                let area = m.geometry;
+                x >= area.x && x < area.x + area.width as i32 && y >= area.y && y < area.y + area.height as i32
+            })
+            .unwrap_or(std::cmp::min(self.current_monitor, self.monitors.len() - 1))
+    }
+
+    /// Query the monitors again, after a screen change. Clients of removed
+    /// monitors move to the first one.
+    fn update_monitors(&mut self) {
+        let previous = std::mem::take(&mut self.monitors);
+        self.monitors = monitor::query(self.display, self.root, self.randr.as_ref(), self.screen());
+        let n = self.monitors.len();
+        eprintln!("{} monitor(s)", n);
+        // Remaining monitors, found by their geometry, keep their workspace
+        // and their clients. Indices of removed monitors become n.
+        let new_index: Vec<usize> = previous.iter()
+            .map(|old| self.monitors.iter().position(|m| m.geometry == old.geometry).unwrap_or(n))
+            .collect();
+        for (i, monitor) in self.monitors.iter_mut().enumerate() {
+            monitor.workspace = new_index.iter().position(|&j| j == i)
+                .map_or(self.current_workspace, |j| previous[j].workspace);
+        }
+        self.current_monitor = new_index.get(self.current_monitor).copied().filter(|&i| i < n).unwrap_or(0);
+        for client in self.clients.iter_mut() {
+            client.monitor = new_index.get(client.monitor).copied().unwrap_or(n);
+        }
+        let homeless: Vec<xlib::Window> = self.clients.iter()
+            .filter(|client| client.monitor >= n)
+            .map(|client| client.window)
+            .collect();
+        for w in homeless {
+            self.move_to_monitor(w, 0);
+        }
+        self.assign_workspaces();
+        self.refit_placed_clients();
+        self.update_workarea();
+        self.arrange();
+    }
+
+    /// Fit fullscreen and maximized clients to their monitor again, after it
+    /// or its work area changed.
+    fn refit_placed_clients(&mut self) {
+        let placed: Vec<xlib::Window> = self.clients.iter()
+            .filter(|client| client.fullscreen || client.maximized_vert || client.maximized_horz)
+            .map(|client| client.window)
+            .collect();
+        for w in placed {
+            self.apply_geometry(w);
+        }
+    }
+
+    /// Show client window `w` on monitor `monitor`, at the same position
+    /// relative to the monitor if it is floating.
+    fn move_to_monitor(&mut self, w: xlib::Window, monitor: usize) {
+        let client = &self.clients[w];
+        let to = self.monitors[monitor].geometry;
+        let mut geometry = client.saved_geometry.unwrap_or(client.geometry);
+        let from = match self.monitors.get(client.monitor) {
+            Some(m) => m.geometry,
+            None => Rect { x: geometry.x, y: geometry.y, ..to },
+        };
+        // Keep the window within the work area of the new monitor.
+        geometry.x += to.x - from.x;
+        geometry.y += to.y - from.y;
+        let geometry = self.within_work_area(geometry, monitor);
+        let client = self.clients.get_mut(w).unwrap();
+        client.monitor = monitor;
+        if client.saved_geometry.is_some() {
+            client.saved_geometry = Some(geometry);
+            self.apply_geometry(w);
+        } else {
+            self.move_resize(w, geometry);
+        }
+    }
+
+    /// Publish the work area, which is the same on every desktop.
+    fn update_workarea(&self) {
+        let area = self.work_area();
+        let values: Vec<u64> = (0..self.workspaces.len())
+            .flat_map(|_| vec![area.x as u64, area.y as u64, area.width as u64, area.height as u64])
+            .collect();
+        ewmh::set_cardinals(self.display, self.root, self.atoms.net_workarea, &values);
+    }
+
+    /// Stop reserving the strut of window `w`, if any.
+    fn forget_strut(&mut self, w: xlib::Window) {
+        if self.struts.iter().any(|&(x, _)| x == w) {
+            self.struts.retain(|&(x, _)| x != w);
+            self.update_workarea();
+            self.refit_placed_clients();
+            self.arrange();
+        }
+    }
+
+    /// Geometry `geometry` moved to the middle of the work area of monitor
+    /// `monitor`.
+    fn centered(&self, geometry: Rect, monitor: usize) -> Rect {
+        self.centered_over(geometry, self.monitor_work_area(monitor))
+    }
+
+    /// Geometry `geometry` moved so that its frame, including the border,
+    /// lies within the work area of monitor `monitor`. A frame larger than the
+    /// work area starts at its top left corner.
+    fn within_work_area(&self, geometry: Rect, monitor: usize) -> Rect {
+        let area = self.monitor_work_area(monitor);
+        let border = 2 * self.config.border_width as i32;
+        let max_x = area.x + area.width as i32 - (geometry.width as i32 + border);
+        let max_y = area.y + area.height as i32 - (geometry.height as i32 + border);
+        Rect {
+            x: geometry.x.clamp(area.x, std::cmp::max(max_x, area.x)),
+            y: geometry.y.clamp(area.y, std::cmp::max(max_y, area.y)),
+            ..geometry
+        }
+    }
+
+    /// Geometry `geometry` moved to the middle of `area`.
+    fn centered_over(&self, geometry: Rect, area: Rect) -> Rect {
+        let border = 2 * self.config.border_width;
+        Rect {
+            x: area.x + (area.width as i32 - (geometry.width + border) as i32) / 2,
+            y: area.y + (area.height as i32 - (geometry.height + border) as i32) / 2,
+            ..geometry
+        }
+    }
+
+    /// Place the clients of the visible workspaces according to their layout,
+    /// if they are in tiling mode. Floating clients are left where they are.
+    fn arrange(&mut self) {
+        // Each monitor has its own share of the clients.
+        let mut placements = Vec::new();
+        for monitor in 0..self.monitors.len() {
+            let n = self.monitors[monitor].workspace;
+            let workspace = &self.workspaces[n];
+            if !workspace.tiled {
+                continue;
+            }
+            let tiled: Vec<xlib::Window> = self.clients.on_workspace(n)
+                .filter(|client| client.is_tiled() && client.monitor == monitor)
+                .map(|client| client.window)
+                .collect();
+            let layout = &self.layouts[workspace.layout];
+            let rects = layout.arrange(self.monitor_work_area(monitor), &tiled, &workspace.layout_params);
+            placements.extend(tiled.into_iter().zip(rects));
+        }
+        for &(w, rect) in &placements {
+            self.place(w, rect);
+        }
+        self.discard_enter_events();
+        // Keep the focused client visible when clients overlap (monocle).
+        if let Some(w) = self.focused.filter(|&w| placements.iter().any(|&(x, _)| x == w)) {
+            self.raise(w);
+        }
+    }
+
+    /// Move and resize client window `w` so that its frame, including the
+    /// border, covers `rect`.
+    fn place(&mut self, w: xlib::Window, rect: Rect) {
+        let border = 2 * self.config.border_width;
+        let width = std::cmp::max(rect.width.saturating_sub(border), 1);
+        let height = std::cmp::max(rect.height.saturating_sub(border), 1);
+        self.move_resize(w, Rect { x: rect.x, y: rect.y, width, height });
+    }
+
+    /// Move the frame of client window `w` to the position of `geometry` and
+    /// resize both to its size, as constrained by the size hints of the
+    /// client unless it is fullscreen. The client stays on its monitor.
+    fn move_resize(&mut self, w: xlib::Window, geometry: Rect) {
+        let client = self.clients.get_mut(w).unwrap();
+        let mut geometry = geometry;
+        if !client.fullscreen {
+            let (width, height) = client.size_hints.constrain(geometry.width, geometry.height);
+            geometry.width = width;
+            geometry.height = height;
+        }
+        client.geometry = geometry;
+        unsafe {
+            xlib::XMoveResizeWindow(self.display, client.frame, geometry.x, geometry.y, geometry.width, geometry.height);
+            xlib::XResizeWindow(self.display, w, geometry.width, geometry.height);
+        }
+        self.send_configure_notify(w);
+    }
+
+    /// Tell client window `w` its position relative to the root window. The
+    /// real ConfigureNotify events of a reparented window only give its
+    /// position within its frame, and none is sent when only the frame moves.
+    fn send_configure_notify(&self, w: xlib::Window) {
+        let client = &self.clients[w];
+        let border = if client.fullscreen { 0 } else { self.config.border_width as i32 };
+        let mut event = xlib::XEvent {
+            configure: xlib::XConfigureEvent {
+                type_: xlib::ConfigureNotify,
+                serial: 0,
+                send_event: 1,
+                display: self.display,
+                event: w,
+                window: w,
+                x: client.geometry.x + border,
+                y: client.geometry.y + border,
+                width: client.geometry.width as i32,
+                height: client.geometry.height as i32,
+                border_width: 0,
+                above: 0,
+                override_redirect: 0,
+            }
+        };
+        unsafe { xlib::XSendEvent(self.display, w, 0, xlib::StructureNotifyMask, &mut event) };
+    }
+
+    /// Raise the frame of client window `w` to the top of its layer.
+    fn raise(&mut self, w: xlib::Window) {
+        self.clients.raise(w);
+        self.restack();
+    }
+
+    /// Lower the frame of client window `w` to the bottom of its layer.
+    fn lower(&mut self, w: xlib::Window) {
+        self.clients.lower(w);
+        self.restack();
+    }
+
+    /// Stack the frames in the order of the client model.
+    fn restack(&mut self) {
+        let stacking: Vec<xlib::Window> = self.clients.stacking().iter().rev().copied().collect();
+        // Docks stay above every client but the fullscreen ones shown on their
+        // monitor, and what is stacked above them: right below the lowest of
+        // these clients, if any, since `windows` goes from top to bottom.
+        // Desktop windows stay below everything.
+        let below_fullscreen: Vec<Option<xlib::Window>> = self.docks.iter()
+            .map(|&(_, geometry)| {
+                let monitor = self.monitor_at(geometry);
+                stacking.iter().rev().copied().find(|&w| {
+                    let client = &self.clients[w];
+                    client.fullscreen && !client.hidden && client.monitor == monitor && self.is_visible(client.workspace)
+                })
+            })
+            .collect();
+        let docks_below = |w: Option<xlib::Window>| self.docks.iter()
+            .zip(&below_fullscreen)
+            .filter(move |&(_, &above)| above == w)
+            .map(|(&(dock, _), _)| dock);
+        let mut windows: Vec<xlib::Window> = docks_below(None).collect();
+        for &w in &stacking {
+            windows.push(self.clients[w].frame);
+            windows.extend(docks_below(Some(w)));
+        }
+        unsafe {
+            xlib::XRestackWindows(self.display, windows.as_mut_ptr(), windows.len() as i32);
+            for &w in &self.desktop_windows {
+                xlib::XLowerWindow(self.display, w);
+            }
+        }
+        self.update_client_list();
+        self.discard_enter_events();
+    }
+
+    /// Drop the pending EnterNotify events, caused by windows moving under the
+    /// pointer rather than by the pointer moving, so that they don't steal the
+    /// focus.
+    fn discard_enter_events(&self) {
+        let mut e = xlib::XEvent { pad: [0; 24] };
+        unsafe {
+            xlib::XSync(self.display, 0);
+            while xlib::XCheckMaskEvent(self.display, xlib::EnterWindowMask, &mut e) > 0 {}
+        }
+    }
+
+    /// Add (`on`) or remove state `state`, a _NET_WM_STATE atom, of client
+    /// window `w`.
+    fn set_state(&mut self, w: xlib::Window, state: xlib::Atom, on: bool) {
+        let atoms = &self.atoms;
+        let client = self.clients.get_mut(w).unwrap();
+        if state == atoms.net_wm_state_fullscreen {
+            client.fullscreen = on;
+            self.apply_geometry(w);
+            // Docks come back above a client leaving fullscreen.
+            self.restack();
+        } else if state == atoms.net_wm_state_maximized_vert {
+            client.maximized_vert = on;
+            self.apply_geometry(w);
+        } else if state == atoms.net_wm_state_maximized_horz {
+            client.maximized_horz = on;
+            self.apply_geometry(w);
+        } else if state == atoms.net_wm_state_above {
+            client.above = on;
+            self.clients.sort_stack();
+            self.restack();
+        } else if state == atoms.net_wm_state_below {
+            client.below = on;
+            self.clients.sort_stack();
+            self.restack();
+        } else if state == atoms.net_wm_state_sticky {
+            client.sticky = on;
+            // A sticky client follows the workspace of its monitor.
+            let from = client.workspace;
+            if on && !self.is_visible(from) {
+                self.clients.get_mut(w).unwrap().workspace = self.current_workspace;
+                self.workspaces[from].forget(w);
+                self.show_workspaces();
+            }
+            self.update_client_desktop(w);
+        } else if state == atoms.net_wm_state_hidden {
+            client.hidden = on;
+            let (frame, workspace) = (client.frame, client.workspace);
+            if on {
+                unsafe { xlib::XUnmapWindow(self.display, frame) };
+                self.workspaces[workspace].forget(w);
+                if self.focused == Some(w) {
+                    self.focus_last();
+                }
+            } else if self.is_visible(workspace) {
+                unsafe { xlib::XMapWindow(self.display, frame) };
+            }
+            self.restack();
+        } else if state == atoms.net_wm_state_demands_attention {
+            client.urgent = on;
+            self.update_border(w);
+        } else {
+            eprintln!("Ignore unsupported state {} of window {}", state, w);
+            return;
+        }
+        self.update_client_state(w);
+        self.arrange();
+    }
+
+    /// Move and resize client window `w` according to its fullscreen and
+    /// maximized states, saving or restoring its floating geometry.
+    fn apply_geometry(&mut self, w: xlib::Window) {
+        let monitor = self.clients[w].monitor;
+        let (screen, area) = (self.monitors[monitor].geometry, self.monitor_work_area(monitor));
+        let client = self.clients.get_mut(w).unwrap();
+        let maximized = client.maximized_vert || client.maximized_horz;
+        if client.fullscreen || maximized {
+            if client.saved_geometry.is_none() {
+                client.saved_geometry = Some(client.geometry);
+            }
+        } else if let Some(saved) = client.saved_geometry.take() {
+            unsafe { xlib::XSetWindowBorderWidth(self.display, client.frame, self.config.border_width) };
+            self.move_resize(w, saved);
+            return;
+        } else {
+            return;
+        }
+        let saved = client.saved_geometry.unwrap();
+        if client.fullscreen {
+            // The border would fall outside of the screen.
+            unsafe { xlib::XSetWindowBorderWidth(self.display, client.frame, 0) };
+            self.move_resize(w, screen);
+            self.raise(w);
+            return;
+        }
+        unsafe { xlib::XSetWindowBorderWidth(self.display, client.frame, self.config.border_width) };
+        let border = 2 * self.config.border_width;
+        let mut geometry = saved;
+        if client.maximized_vert {
+            geometry.y = area.y;
+            geometry.height = std::cmp::max(area.height.saturating_sub(border), 1);
+        }
+        if client.maximized_horz {
+            geometry.x = area.x;
+            geometry.width = std::cmp::max(area.width.saturating_sub(border), 1);
+        }
+        self.move_resize(w, geometry);
+    }
+
+    /// Paint the border of client window `w` in the urgent color if it
+    /// demands attention, unless presentation mode hides it.
+    fn update_border(&self, w: xlib::Window) {
+        let client = &self.clients[w];
+        let color = if client.urgent && self.presentation.shows_urgency(w) {
+            self.config.urgent_color
+        } else {
+            self.config.border_color
+        };
+        unsafe { xlib::XSetWindowBorder(self.display, client.frame, color) };
+    }
+
+    /// Whether client window `w` has state `state`, a _NET_WM_STATE atom.
+    fn has_state(&self, w: xlib::Window, state: xlib::Atom) -> bool {
+        let client = &self.clients[w];
+        let atoms = &self.atoms;
+        if state == atoms.net_wm_state_fullscreen { client.fullscreen }
+        else if state == atoms.net_wm_state_maximized_vert { client.maximized_vert }
+        else if state == atoms.net_wm_state_maximized_horz { client.maximized_horz }
+        else if state == atoms.net_wm_state_above { client.above }
+        else if state == atoms.net_wm_state_below { client.below }
+        else if state == atoms.net_wm_state_sticky { client.sticky }
+        else if state == atoms.net_wm_state_hidden { client.hidden }
+        else if state == atoms.net_wm_state_demands_attention { client.urgent }
+        else { false }
+    }
+
+    /// Publish the states of client window `w` as its _NET_WM_STATE.
+    fn update_client_state(&self, w: xlib::Window) {
+        let client = &self.clients[w];
+        let states = [
+            (client.fullscreen, self.atoms.net_wm_state_fullscreen),
+            (client.maximized_vert, self.atoms.net_wm_state_maximized_vert),
+            (client.maximized_horz, self.atoms.net_wm_state_maximized_horz),
+            (client.above, self.atoms.net_wm_state_above),
+            (client.below, self.atoms.net_wm_state_below),
+            (client.sticky, self.atoms.net_wm_state_sticky),
+            (client.hidden, self.atoms.net_wm_state_hidden),
+            (client.urgent, self.atoms.net_wm_state_demands_attention),
+        ];
+        let atoms: Vec<xlib::Atom> = states.iter().filter(|(on, _)| *on).map(|&(_, atom)| atom).collect();
+        ewmh::set_atoms(self.display, w, self.atoms.net_wm_state, &atoms);
+    }
+
+    /// Create the supporting window manager check window and advertise the
+    /// supported hints on the root window.
+    fn setup_ewmh(&mut self) {
+        unsafe {
+            self.check_window = xlib::XCreateSimpleWindow(self.display, self.root, -1, -1, 1, 1, 0, 0, 0);
+        }
+        let check = [self.check_window];
+        ewmh::set_windows(self.display, self.root, self.atoms.net_supporting_wm_check, &check);
+        ewmh::set_windows(self.display, self.check_window, self.atoms.net_supporting_wm_check, &check);
+        ewmh::set_string(self.display, self.check_window, &self.atoms, self.atoms.net_wm_name, "rswm");
+        ewmh::set_atoms(self.display, self.root, self.atoms.net_supported, &self.atoms.supported());
+        self.update_client_list();
+        self.update_desktops();
+    }
+
+    /// Publish the managed clients, in mapping and stacking order.
+    fn update_client_list(&self) {
+        ewmh::set_windows(self.display, self.root, self.atoms.net_client_list, self.clients.windows());
+        ewmh::set_windows(self.display, self.root, self.atoms.net_client_list_stacking, self.clients.stacking());
+    }
+
+    /// Publish the workspaces as desktops. There are no large desktops, so
+    /// every viewport is at the origin.
+    fn update_desktops(&self) {
+        let n = self.workspaces.len();
+        let mut names = String::new();
+        for name in &self.config.workspaces {
+            names.push_str(name);
+            names.push('\0');
+        }
+        ewmh::set_cardinals(self.display, self.root, self.atoms.net_number_of_desktops, &[n as u64]);
+        ewmh::set_string(self.display, self.root, &self.atoms, self.atoms.net_desktop_names, &names);
+        ewmh::set_cardinals(self.display, self.root, self.atoms.net_desktop_viewport, &vec![0; 2 * n]);
+        ewmh::set_cardinals(self.display, self.root, self.atoms.net_current_desktop, &[self.current_workspace as u64]);
+        self.update_workarea();
+    }
+
+    /// Publish the workspace of client window `w` as its _NET_WM_DESKTOP.
+    fn update_client_desktop(&self, w: xlib::Window) {
+        let client = &self.clients[w];
+        let n = if client.sticky { ewmh::ALL_DESKTOPS } else { client.workspace as u64 };
+        ewmh::set_cardinals(self.display, w, self.atoms.net_wm_desktop, &[n]);
+    }
+
+    /// Hide the current workspace and show workspace `n` instead. In
+    /// per-monitor mode, this is on the current monitor, and a workspace shown
+    /// on another monitor is swapped with the current one.
+    fn switch_workspace(&mut self, n: usize) {
+        if n == self.current_workspace || n >= self.workspaces.len() {
+            return;
+        }
+        // 1. Choose the workspaces of the monitors.
+        let current = self.current_workspace;
+        let shown: Vec<usize> = self.monitors.iter().map(|m| m.workspace).collect();
+        match self.config.workspace_mode {
+            WorkspaceMode::Global => for monitor in &mut self.monitors {
+                monitor.workspace = n;
+            },
+            WorkspaceMode::PerMonitor => {
+                if let Some(other) = self.monitor_of_workspace(n) {
+                    self.monitors[other].workspace = current;
+                }
+                self.monitors[self.current_monitor].workspace = n;
+            },
+        }
+        // 2. Sticky clients stay on their monitor, so they follow the
+        // workspace it now shows.
+        let sticky: Vec<(xlib::Window, usize, usize)> = self.clients.iter()
+            .filter(|client| client.sticky)
+            .filter_map(|client| {
+                let monitor = shown.iter().position(|&i| i == client.workspace)?;
+                Some((client.window, client.workspace, self.monitors[monitor].workspace))
+            })
+            .collect();
+        for (w, from, to) in sticky {
+            self.clients.get_mut(w).unwrap().workspace = to;
+            self.workspaces[from].forget(w);
+        }
+        // 3. Show them.
+        self.previous_workspace = self.current_workspace;
+        self.current_workspace = n;
+        ewmh::set_cardinals(self.display, self.root, self.atoms.net_current_desktop, &[n as u64]);
+        self.show_workspaces();
+        eprintln!("Switched to workspace {}", self.config.workspaces[n]);
+        self.restack();
+        self.arrange();
+        self.focus_last();
+    }
+
+    /// Send client window `w` to workspace `n`.
+    fn move_to_workspace(&mut self, w: xlib::Window, n: usize) {
+        let client = self.clients.get_mut(w).unwrap();
+        let from = client.workspace;
+        if from == n || n >= self.workspaces.len() {
+            return;
+        }
+        client.workspace = n;
+        // Sending a sticky client somewhere pins it there.
+        client.sticky = false;
+        self.workspaces[from].forget(w);
+        self.update_client_desktop(w);
+        self.update_client_state(w);
+        // Dialogs follow their parent.
+        for transient in self.clients.transients_of(w) {
+            self.move_to_workspace(transient, n);
+        }
+        self.show_workspaces();
+        if n != self.current_workspace && self.focused == Some(w) {
+            self.focus_last();
+        }
+        self.restack();
+        self.arrange();
+    }
+
+    fn close(&self, w: xlib::Window) {
+        // There are two ways to tell an X window to close. The first is to send it
+        // a message of type WM_PROTOCOLS and value WM_DELETE_WINDOW. If the client
+        // has not explicitly marked itself as supporting this more civilized
+        // behavior (using XSetWMProtocols()), we kill it with XKillClient().
+        if self.supports_protocol(w, self.wm_delete_window) {
+            eprintln!("Gracefully deleting window {}",w);
+            self.send_protocol(w, self.wm_delete_window);
+        } else {
+            eprintln!("Killing window {}",w);
+            unsafe { xlib::XKillClient(self.display, w) };
+        }
+    }
+
+    /// Whether window `w` takes part in WM_PROTOCOLS protocol `protocol`.
+    fn supports_protocol(&self, w: xlib::Window, protocol: xlib::Atom) -> bool {
+        let mut supported_protocols: *mut xlib::Atom = std::ptr::null_mut();
+        let mut num_supported_protocols = 0;
+        unsafe {
+            if xlib::XGetWMProtocols(self.display, w, &mut supported_protocols, &mut num_supported_protocols) == 0 {
+                return false;
+            }
+            let supported = in_list(supported_protocols, num_supported_protocols, protocol);
+            xlib::XFree(supported_protocols as *mut std::ffi::c_void);
+            supported
+        }
+    }
+
+    /// Send a WM_PROTOCOLS message of value `protocol` to window `w`.
+    fn send_protocol(&self, w: xlib::Window, protocol: xlib::Atom) {
+        // 1. Construct message.
+        let mut data = xlib::ClientMessageData::new();
+        data.set_long(0, protocol as i64);
+        data.set_long(1, xlib::CurrentTime as i64);
+        let mut msg = xlib::XEvent {
+            client_message: xlib::XClientMessageEvent {
+                type_: xlib::ClientMessage,
+                message_type: self.wm_protocols,
+                window: w,
+                format: 32,
+                data,
+                send_event: 0,
+                display: std::ptr::null_mut(),
+                serial: 0,
+            }
+        };
+        // 2. Send message to window.
+        unsafe { assert!(xlib::XSendEvent(self.display, w, 0, 0, &mut msg) > 0) };
+    }
+
+    /// Raise and focus the client following (or preceding) the focused one in
+    /// the focus history of the current workspace, most recently focused
+    /// first. The history is only updated when the cycle ends, once the
+    /// modifiers of the binding are released, so that pressing the binding
+    /// repeatedly goes further back. While presenting, only shared windows are
+    /// cycled through, if any.
+    fn focus_next(&mut self, forward: bool) {
+        // 1. Start a cycle, unless one is going on. Clients never focused come
+        // after the history, in creation order.
+        if self.cycle.as_ref().is_some_and(|cycle| cycle.workspace != self.current_workspace) {
+            self.end_cycle();
+        }
+        if self.cycle.is_none() {
+            let mut windows: Vec<xlib::Window> = self.workspaces[self.current_workspace].focus_history.clone();
+            for client in self.clients.on_workspace(self.current_workspace) {
+                if !windows.contains(&client.window) {
+                    windows.push(client.window);
+                }
+            }
+            windows.retain(|&w| !self.clients[w].hidden && self.presentation.can_cycle_to(w));
+            self.cycle = Some(FocusCycle { workspace: self.current_workspace, windows, modifiers: 0 });
+        }
+        // 2. Find next window. Without focused client, start from the first one.
+        let windows = &self.cycle.as_ref().unwrap().windows;
+        let n = windows.len();
+        if n == 0 {
+            return;
+        }
+        let next = match self.focused.and_then(|w| windows.iter().position(|&x| x == w)) {
+            None => windows[0],
+            Some(i) => if forward { windows[(i + 1) % n] } else { windows[(i + n - 1) % n] },
+        };
+        // 3. Raise and focus it.
+        self.focus(next);
+    }
+
+    /// Exchange the places of client windows `w` and `x`: their places in the
+    /// layout if they are tiled, their positions otherwise.
+    fn swap(&mut self, w: xlib::Window, x: xlib::Window) {
+        self.clients.swap(w, x);
+        // Fullscreen and maximized clients keep their place.
+        let by_hand = |client: &Client| (client.floating || !self.workspaces[client.workspace].tiled)
+            && !(client.fullscreen || client.maximized_vert || client.maximized_horz);
+        let (a, b) = (self.clients[w].geometry, self.clients[x].geometry);
+        let (move_w, move_x) = (by_hand(&self.clients[w]), by_hand(&self.clients[x]));
+        if move_w {
+            self.move_resize(w, Rect { x: b.x, y: b.y, ..a });
+        }
+        if move_x {
+            self.move_resize(x, Rect { x: a.x, y: a.y, ..b });
+        }
+        self.arrange();
+        self.update_client_list();
+    }
+
+    /// End focus cycling: the client it stopped on becomes the most recently
+    /// focused one.
+    fn end_cycle(&mut self) {
+        if let Some(cycle) = self.cycle.take() {
+            if cycle.modifiers != 0 {
+                unsafe { xlib::XUngrabKeyboard(self.display, xlib::CurrentTime) };
+            }
+        }
+        if let Some(w) = self.focused {
+            self.workspaces[self.clients[w].workspace].touch(w);
+        }
+    }
+
+    /// Raise client window `w` and give it the input focus.
+    fn focus(&mut self, w: xlib::Window) {
+        self.raise(w);
+        self.give_focus(w);
+    }
+
+    /// Focus client window `w`, clicked or entered with the pointer. It is
+    /// raised if the configuration says so.
+    fn focus_by_pointer(&mut self, w: xlib::Window) {
+        if self.config.raise_on_focus {
+            self.focus(w);
+        } else {
+            self.give_focus(w);
+        }
+    }
+
+    /// Give client window `w` the input focus, without raising it.
+    fn give_focus(&mut self, w: xlib::Window) {
+        self.set_current_monitor(self.clients[w].monitor);
+        // Clients that set the focus themselves, if any, only ask to be told
+        // with WM_TAKE_FOCUS.
+        if self.clients[w].accepts_input {
+            unsafe { xlib::XSetInputFocus(self.display, w, xlib::RevertToPointerRoot, xlib::CurrentTime) };
+        }
+        if self.supports_protocol(w, self.wm_take_focus) {
+            self.send_protocol(w, self.wm_take_focus);
+        }
+        self.set_focused(Some(w));
+        // While cycling, the history waits for the end of the cycle.
+        if self.cycle.is_none() {
+            self.workspaces[self.clients[w].workspace].touch(w);
+        }
+        if self.clients[w].urgent {
+            self.set_state(w, self.atoms.net_wm_state_demands_attention, false);
+        }
+    }
+
+    /// Record the focused client and publish it as _NET_ACTIVE_WINDOW.
+    fn set_focused(&mut self, w: Option<xlib::Window>) {
+        let previous = std::mem::replace(&mut self.focused, w);
+        // Only unfocused clients are focused by a click.
+        if previous != w {
+            for &x in [previous, w].iter().flatten() {
+                if self.clients.contains(x) {
+                    self.grab_buttons(x);
+                }
+            }
+        }
+        ewmh::set_windows(self.display, self.root, self.atoms.net_active_window, &[w.unwrap_or(0)]);
+    }
+
+    /// Make monitor `monitor` the current one and focus its last focused client
+    /// on the current workspace, if any.
+    fn focus_monitor(&mut self, monitor: usize) {
+        self.set_current_monitor(monitor);
+        let last = self.workspaces[self.current_workspace].focus_history.iter()
+            .copied()
+            .find(|&w| self.clients[w].monitor == monitor && !self.clients[w].hidden);
+        match last {
+            Some(w) => self.focus(w),
+            None => {
+                unsafe { xlib::XSetInputFocus(self.display, xlib::PointerRoot as u64, xlib::RevertToPointerRoot, xlib::CurrentTime) };
+                self.set_focused(None);
+            }
+        }
+        eprintln!("Focused monitor {}", monitor);
+    }
+
+    /// Focus the last focused client of the current workspace, if any.
+    fn focus_last(&mut self) {
+        let last = self.workspaces[self.current_workspace].last_focused()
+            .or_else(|| self.clients.on_workspace(self.current_workspace)
+                     .filter(|client| !client.hidden)
+                     .last()
+                     .map(|client| client.window));
+        match last {
+            Some(w) => self.focus(w),
+            None => {
+                unsafe { xlib::XSetInputFocus(self.display, xlib::PointerRoot as u64, xlib::RevertToPointerRoot, xlib::CurrentTime) };
+                self.set_focused(None);
+            }
+        }
+    }
+
+    fn on_key_release(&mut self, e: &xlib::XKeyEvent) {
+        // Releasing the modifier of the binding ends focus cycling.
+        let modifiers = match &self.cycle {
+            Some(cycle) => cycle.modifiers,
+            None => return,
+        };
+        if self.modifier_mask(e.keycode) & modifiers != 0 {
+            self.end_cycle();
+        }
+    }
+
+    fn on_mapping_notify(&mut self, e: &mut xlib::XMappingEvent) {
+        unsafe { xlib::XRefreshKeyboardMapping(e) };
+        // Key codes or modifiers changed: grab the bindings again.
+        if e.request == xlib::MappingKeyboard || e.request == xlib::MappingModifier {
+            for client in self.clients.iter() {
+                self.ungrab_buttons(client.window);
+            }
+            self.update_lock_masks();
+            self.grab_keys();
+            for client in self.clients.iter() {
+                self.grab_buttons(client.window);
+            }
+        }
+    }
+
+    fn on_property_notify(&mut self, e: &xlib::XPropertyEvent) {
+        if e.atom == xlib::XA_WM_HINTS {
+            if !self.clients.contains(e.window) {
+                return;
+            }
+            let wm_hints = WmHints::read(self.display, e.window);
+            self.clients.get_mut(e.window).unwrap().accepts_input = wm_hints.input;
+            // The focused client needs no attention.
+            if wm_hints.urgent != self.clients[e.window].urgent && self.focused != Some(e.window) {
+                self.set_state(e.window, self.atoms.net_wm_state_demands_attention, wm_hints.urgent);
+            }
+        } else if e.atom == xlib::XA_WM_NORMAL_HINTS {
+            if let Some(client) = self.clients.get_mut(e.window) {
+                client.size_hints = SizeHints::read(self.display, e.window);
+                // Bring the current size within the new hints.
+                let geometry = client.geometry;
+                self.move_resize(e.window, geometry);
+                self.arrange();
+            }
+        } else if e.atom == self.atoms.net_wm_strut || e.atom == self.atoms.net_wm_strut_partial {
+            if !self.docks.iter().any(|&(w, _)| w == e.window) && !self.clients.contains(e.window) {
+                return;
+            }
+            self.struts.retain(|&(w, _)| w != e.window);
+            if let Some(strut) = Strut::read(self.display, e.window, &self.atoms, self.screen()) {
+                self.struts.push((e.window, strut));
+            }
+            self.update_workarea();
+            self.refit_placed_clients();
+            self.arrange();
+        }
+    }
+
+    fn on_screen_change(&mut self, e: &mut xlib::XEvent) {
+        if let Some(randr) = &self.randr {
+            randr.update_configuration(e);
+        }
+        self.update_monitors();
+    }
+
+    fn on_client_message(&mut self, e: &xlib::XClientMessageEvent) {
+        if e.message_type == self.atoms.net_active_window {
+            if !self.clients.contains(e.window) {
+                return;
+            }
+            // Applications must not steal the focus during a presentation, but
+            // the user may still pick a window from a taskbar.
+            if self.presentation.active && e.data.get_long(0) == ewmh::SOURCE_APPLICATION {
+                eprintln!("Ignore activation of window {} while presenting", e.window);
+                return;
+            }
+            let workspace = self.clients[e.window].workspace;
+            if !self.is_visible(workspace) {
+                self.switch_workspace(workspace);
+            }
+            if self.clients[e.window].hidden {
+                self.set_state(e.window, self.atoms.net_wm_state_hidden, false);
+            }
+            self.focus(e.window);
+        } else if e.message_type == self.atoms.net_current_desktop {
+            // Xlib sign-extends the 32-bit data of client messages.
+            self.switch_workspace(e.data.get_long(0) as u32 as usize);
+        } else if e.message_type == self.atoms.net_wm_desktop {
+            let n = e.data.get_long(0) as u32 as u64;
+            if !self.clients.contains(e.window) {
+                return;
+            }
+            if n == ewmh::ALL_DESKTOPS {
+                self.set_state(e.window, self.atoms.net_wm_state_sticky, true);
+            } else {
+                self.move_to_workspace(e.window, n as usize);
+            }
+        } else if e.message_type == self.atoms.net_wm_state {
+            let w = e.window;
+            if !self.clients.contains(w) {
+                return;
+            }
+            // A message changes one or two states, the second one being 0 if
+            // unused (e.g. both maximized states at once).
+            let action = e.data.get_long(0);
+            for i in 1..3 {
+                let state = e.data.get_long(i) as xlib::Atom;
+                if state == 0 {
+                    continue;
+                }
+                let on = match action {
+                    ewmh::STATE_REMOVE => false,
+                    ewmh::STATE_ADD => true,
+                    ewmh::STATE_TOGGLE => !self.has_state(w, state),
+                    _ => continue,
+                };
+                self.set_state(w, state, on);
+            }
+        } else if e.message_type == self.atoms.net_number_of_desktops {
+            eprintln!("Ignore request for {} desktops, workspaces are set in the configuration", e.data.get_long(0));
+        } else {
+            eprintln!("Ignore client message {} for window {}", e.message_type, e.window);
+        }
+    }
+
+    fn on_motion_notify(&mut self, e: &xlib::XMotionEvent) {
+        match self.clients.resolve(e.window){
+            None => {},
+            Some(w) =>
             {
                 let drag_pos = Position { x:e.x_root, y:e.y_root };
                 let delta_x = drag_pos.x - self.drag_start_pos.x;
                 let delta_y = drag_pos.y - self.drag_start_pos.y;
+                let geometry = self.clients[w].geometry;
 
-                if (e.state & xlib::Button1Mask) > 0 {
-                    // ctrl + left button: Move window.
-                    let dest_frame_pos_x = self.drag_start_frame_pos.x + delta_x;
-                    let dest_frame_pos_y = self.drag_start_frame_pos.y + delta_y;
-                    unsafe {xlib::XMoveWindow( self.display, *frame, dest_frame_pos_x, dest_frame_pos_y) };
-                } else if (e.state & xlib::Button3Mask) > 0 {
-                    // ctrl + right button: Resize window.
-                    // Window dimensions cannot be negative.
-                    let new_width:i32 = self.drag_start_frame_size.x + delta_x;
-                    let new_width2:u32 = if new_width > 0 { new_width as u32 } else { 0 };
-                    let new_height = self.drag_start_frame_size.y + delta_y;
-                    let new_height2:u32 = if new_height > 0 { new_height as u32 } else { 0 };
-                    unsafe {
-                        // 1. Resize frame.
-                        xlib::XResizeWindow( self.display, *frame, new_width2, new_height2);
-                        // 2. Resize client window.
-                        xlib::XResizeWindow( self.display, e.window, new_width2, new_height2);
-                    }
+                match self.drag_action {
+                    None => {},
+                    Some(ButtonAction::Move) => {
+                        // Move window.
+                        let dest_frame_pos_x = self.drag_start_frame_pos.x + delta_x;
+                        let dest_frame_pos_y = self.drag_start_frame_pos.y + delta_y;
+                        self.move_resize(w, Rect { x: dest_frame_pos_x, y: dest_frame_pos_y, ..geometry });
+                    },
+                    Some(ButtonAction::Resize) => {
+                        // Resize window.
+                        // Window dimensions cannot be negative.
+                        let new_width:i32 = self.drag_start_frame_size.x + delta_x;
+                        let new_width2:u32 = if new_width > 0 { new_width as u32 } else { 0 };
+                        let new_height = self.drag_start_frame_size.y + delta_y;
+                        let new_height2:u32 = if new_height > 0 { new_height as u32 } else { 0 };
+                        self.move_resize(w, Rect { width: new_width2, height: new_height2, ..geometry });
+                    },
                 }
             }
         }
     }
 
-    fn run(&mut self) -> () {
+    fn run(&mut self) {
         unsafe { 
             x11::xlib::XSetErrorHandler(Some(WindowManager::on_wm_detected));
-            x11::xlib::XSelectInput( self.display, self.root, x11::xlib::SubstructureRedirectMask | x11::xlib::SubstructureNotifyMask);
+            x11::xlib::XSelectInput( self.display, self.root, x11::xlib::SubstructureRedirectMask | x11::xlib::SubstructureNotifyMask | x11::xlib::EnterWindowMask);
             x11::xlib::XSync(self.display, 0);
             //
             x11::xlib::XSetErrorHandler(Some(WindowManager::on_xerror));
+            //   a. Advertise EWMH support and find the monitors.
+            self.setup_ewmh();
+            self.randr = Randr::init(self.display, self.root);
+            self.update_monitors();
+            self.set_focused(None);
+            //   b. Grab key bindings.
+            self.update_lock_masks();
+            self.grab_keys();
             //   c. Grab X server to prevent windows from changing under us.
             x11::xlib::XGrabServer(self.display);
             //   d. Reparent existing top-level windows.
@@ -578,9 +2207,19 @@ impl WindowManager {
         // 2. Main event loop.
         eprintln!("Entering main loop.");
         loop {
-            // 1. Get next event.
+            // 1. Get next event, handling signals while waiting for it.
             let mut e: xlib::XEvent = xlib::XEvent { pad:[0;24] };
             eprintln!("Waiting for next event");
+            while unsafe { xlib::XPending(self.display) } == 0 {
+                if signal::take_reload() {
+                    self.reload();
+                    continue;
+                }
+                if signal::take_child() {
+                    spawn::reap();
+                }
+                signal::wait(unsafe { xlib::XConnectionNumber(self.display) });
+            }
             unsafe { xlib::XNextEvent(self.display, &mut e) };
             eprintln!("Received event: {:?}", e);
 
@@ -626,6 +2265,10 @@ impl WindowManager {
                 {
                     self.on_button_release(e.as_ref());
                 },
+                xlib::EnterNotify =>
+                {
+                    self.on_enter_notify(e.as_ref());
+                },
                 xlib::MotionNotify =>
                 {
                     // Skip any already pending motion events.
@@ -645,6 +2288,22 @@ impl WindowManager {
                 {
                     self.on_key_release(e.as_ref());
                 },
+                xlib::PropertyNotify =>
+                {
+                    self.on_property_notify(e.as_ref());
+                },
+                xlib::ClientMessage =>
+                {
+                    self.on_client_message(e.as_ref());
+                },
+                xlib::MappingNotify =>
+                {
+                    self.on_mapping_notify(e.as_mut());
+                },
+                t if self.randr.as_ref().is_some_and(|randr| t == randr.screen_change_event) =>
+                {
+                    self.on_screen_change(&mut e);
+                },
                 _ =>
                     eprintln!("Ignored event")
             }
@@ -653,6 +2312,13 @@ impl WindowManager {
 }
 
 fn main() {
-    let mut wm = WindowManager::create();
+    // Configuration errors are reported but should not prevent the window
+    // manager from starting.
+    let config = Config::load().unwrap_or_else(|err| {
+        eprintln!("Invalid configuration: {}\nUsing default configuration.", err);
+        Config::default()
+    });
+    signal::install();
+    let mut wm = WindowManager::create(config);
     wm.run();
 }
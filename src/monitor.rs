@@ -0,0 +1,97 @@
+//! Physical monitors.
+//!
+//! Monitors are queried with XRandR, or with Xinerama on servers without
+//! RandR 1.5.
+
+use std::os::raw::c_void;
+use x11::{xinerama, xlib, xrandr};
+use crate::layout::Rect;
+
+pub struct Monitor {
+    pub geometry: Rect,
+    /// Workspace shown on the monitor.
+    pub workspace: usize,
+}
+
+/// The RandR extension, through which the server reports monitor changes.
+pub struct Randr {
+    /// Type of RRScreenChangeNotify events.
+    pub screen_change_event: i32,
+}
+
+impl Randr {
+    /// Ask for screen change events on `root`. None if the server lacks RandR.
+    pub fn init(display: *mut xlib::Display, root: xlib::Window) -> Option<Randr> {
+        let (mut event_base, mut error_base) = (0, 0);
+        unsafe {
+            if xrandr::XRRQueryExtension(display, &mut event_base, &mut error_base) == 0 {
+                return None;
+            }
+            xrandr::XRRSelectInput(display, root, xrandr::RRScreenChangeNotifyMask);
+        }
+        Some(Randr { screen_change_event: event_base + xrandr::RRScreenChangeNotify })
+    }
+
+    /// Let Xlib know about the new size of the screen.
+    pub fn update_configuration(&self, e: &mut xlib::XEvent) {
+        unsafe { xrandr::XRRUpdateConfiguration(e) };
+    }
+
+    /// Monitors as RandR 1.5 describes them, none on older servers.
+    fn monitors(&self, display: *mut xlib::Display, root: xlib::Window) -> Vec<Monitor> {
+        let mut n = 0;
+        unsafe {
+            let info = xrandr::XRRGetMonitors(display, root, 1, &mut n);
+            if info.is_null() {
+                return Vec::new();
+            }
+            let monitors = std::slice::from_raw_parts(info, n as usize).iter()
+                .map(|m| Monitor { geometry: Rect { x: m.x, y: m.y, width: m.width as u32, height: m.height as u32 }, workspace: 0 })
+                .collect();
+            xrandr::XRRFreeMonitors(info);
+            monitors
+        }
+    }
+}
+
+fn xinerama_monitors(display: *mut xlib::Display) -> Vec<Monitor> {
+    unsafe {
+        if xinerama::XineramaIsActive(display) == 0 {
+            return Vec::new();
+        }
+        let mut n = 0;
+        let info = xinerama::XineramaQueryScreens(display, &mut n);
+        if info.is_null() {
+            return Vec::new();
+        }
+        let monitors = std::slice::from_raw_parts(info, n as usize).iter()
+            .map(|s| Monitor {
+                geometry: Rect { x: s.x_org as i32, y: s.y_org as i32, width: s.width as u32, height: s.height as u32 },
+                workspace: 0,
+            })
+            .collect();
+        xlib::XFree(info as *mut c_void);
+        monitors
+    }
+}
+
+/// Monitors of the screen, from left to right then top to bottom. There is
+/// at least one, the whole screen `screen` if nothing better is known.
+/// Monitors mirroring the same area are merged.
+pub fn query(display: *mut xlib::Display, root: xlib::Window, randr: Option<&Randr>, screen: Rect) -> Vec<Monitor> {
+    let mut monitors = randr.map(|randr| randr.monitors(display, root)).unwrap_or_default();
+    if monitors.is_empty() {
+        monitors = xinerama_monitors(display);
+    }
+    let mut unique: Vec<Monitor> = Vec::new();
+    for monitor in monitors {
+        if !unique.iter().any(|m| m.geometry == monitor.geometry) {
+            unique.push(monitor);
+        }
+    }
+    if unique.is_empty() {
+        unique.push(Monitor { geometry: screen, workspace: 0 });
+    }
+    unique.sort_by_key(|m| (m.geometry.x, m.geometry.y));
+    unique
+}
@@ -0,0 +1,40 @@
+//! Presentation mode, for sharing the screen.
+//!
+//! While presenting, newly mapped windows do not steal the focus, the screen
+//! is never blanked and focus cycling can be restricted to the windows marked
+//! as shared.
+
+use x11::xlib;
+
+#[derive(Default)]
+pub struct Presentation {
+    pub active: bool,
+    /// Whether the mode was turned on by a mapped window of a configured class,
+    /// in which case it is turned off when the last such window goes away.
+    pub automatic: bool,
+    /// Windows shown to the audience.
+    pub shared: Vec<xlib::Window>,
+    /// Mapped clients whose class turns presentation mode on.
+    pub triggers: Vec<xlib::Window>,
+    /// Screen saver timeout, interval, blanking and exposures settings, and
+    /// whether DPMS was enabled, before the mode was turned on.
+    pub saved_screen_saver: Option<(i32, i32, i32, i32, bool)>,
+}
+
+impl Presentation {
+    /// Whether focus cycling may reach `w`.
+    pub fn can_cycle_to(&self, w: xlib::Window) -> bool {
+        !self.active || self.shared.is_empty() || self.shared.contains(&w)
+    }
+
+    /// Whether urgency of `w` may be shown, which would distract the audience
+    /// from the shared windows.
+    pub fn shows_urgency(&self, w: xlib::Window) -> bool {
+        !self.active || self.shared.contains(&w)
+    }
+
+    pub fn forget(&mut self, w: xlib::Window) {
+        self.shared.retain(|&x| x != w);
+        self.triggers.retain(|&x| x != w);
+    }
+}
@@ -0,0 +1,57 @@
+//! Unix signals handled by the window manager.
+//!
+//! Signal handlers only raise a flag. The signals are blocked except while the
+//! main loop waits for X events in `wait`, so a flag can never be raised in the
+//! middle of an event handler and is never missed before going to sleep.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// SIGHUP: reload the configuration file.
+static RELOAD: AtomicBool = AtomicBool::new(false);
+/// SIGCHLD: a spawned program terminated.
+static CHILD: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(signum: libc::c_int) {
+    match signum {
+        libc::SIGHUP => RELOAD.store(true, Ordering::SeqCst),
+        libc::SIGCHLD => CHILD.store(true, Ordering::SeqCst),
+        _ => {}
+    }
+}
+
+/// Install the signal handlers and block the handled signals.
+pub fn install() {
+    unsafe {
+        let mut blocked: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut blocked);
+        for &signum in &[libc::SIGHUP, libc::SIGCHLD] {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            assert_eq!(libc::sigaction(signum, &action, std::ptr::null_mut()), 0);
+            libc::sigaddset(&mut blocked, signum);
+        }
+        assert_eq!(libc::sigprocmask(libc::SIG_BLOCK, &blocked, std::ptr::null_mut()), 0);
+    }
+}
+
+/// Sleep until `fd` is readable or a handled signal is delivered.
+pub fn wait(fd: libc::c_int) {
+    unsafe {
+        let mut unblocked: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut unblocked);
+        let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        // EINTR is the expected way to return when a signal is delivered.
+        libc::ppoll(&mut pollfd, 1, std::ptr::null(), &unblocked);
+    }
+}
+
+/// Whether a configuration reload was requested since the last call.
+pub fn take_reload() -> bool {
+    RELOAD.swap(false, Ordering::SeqCst)
+}
+
+/// Whether a child process terminated since the last call.
+pub fn take_child() -> bool {
+    CHILD.swap(false, Ordering::SeqCst)
+}
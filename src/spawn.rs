@@ -0,0 +1,94 @@
+//! Launching external programs.
+
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+/// Split a command line into words the way a POSIX shell does, honoring single
+/// quotes, double quotes and backslash escapes. Other shell features such as
+/// variables or redirections are not supported.
+pub fn split_words(s: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(w) = word.take() {
+                    words.push(w);
+                }
+            }
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        None => return Err(format!("unterminated quote in '{}'", s)),
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        None => return Err(format!("unterminated quote in '{}'", s)),
+                        Some('"') => break,
+                        // Inside double quotes, a backslash only escapes these.
+                        Some('\\') => match chars.next() {
+                            Some(c) if c == '"' || c == '\\' || c == '$' || c == '`' => w.push(c),
+                            Some(c) => { w.push('\\'); w.push(c) }
+                            None => return Err(format!("unterminated quote in '{}'", s)),
+                        },
+                        Some(c) => w.push(c),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err(format!("trailing backslash in '{}'", s)),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(w) = word {
+        words.push(w);
+    }
+    Ok(words)
+}
+
+/// Start `argv` in the background on X display `display`. The child inherits
+/// the environment of the window manager and is reaped by `reap`.
+pub fn spawn(argv: &[String], display: &str) {
+    let (program, args) = match argv.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut command = Command::new(program);
+    command.args(args).env("DISPLAY", display);
+    // Start the child in its own session, so that it is not affected by signals
+    // sent to the window manager's process group. Note that the standard library
+    // already resets the signal mask we inherit from `signal::install`.
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    match command.spawn() {
+        Ok(child) => eprintln!("Spawned {:?} [{}]", argv, child.id()),
+        Err(err) => eprintln!("Cannot spawn {:?}: {}", argv, err),
+    }
+}
+
+/// Collect the exit status of every terminated child, so that they do not
+/// linger as zombies.
+pub fn reap() {
+    loop {
+        let mut status = 0;
+        let pid = unsafe { libc::waitpid(-1, &mut status, libc::WNOHANG) };
+        if pid <= 0 {
+            break;
+        }
+        eprintln!("Reaped child {}", pid);
+    }
+}
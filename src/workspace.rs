@@ -0,0 +1,38 @@
+//! Virtual desktops.
+//!
+//! Which workspace a client is on is stored in the client itself. A workspace
+//! holds its focus history and how its clients are laid out.
+
+use x11::xlib;
+use crate::layout::LayoutParams;
+
+pub struct Workspace {
+    /// Clients on the workspace, most recently focused first.
+    pub focus_history: Vec<xlib::Window>,
+    /// Whether clients are placed by the layout rather than by hand.
+    pub tiled: bool,
+    /// Index of the layout among `layout::all()`.
+    pub layout: usize,
+    pub layout_params: LayoutParams,
+}
+
+impl Workspace {
+    pub fn new(tiled: bool, layout: usize, layout_params: LayoutParams) -> Workspace {
+        Workspace { focus_history: Vec::new(), tiled, layout, layout_params }
+    }
+
+    /// Forget `w`, which left the workspace.
+    pub fn forget(&mut self, w: xlib::Window) {
+        self.focus_history.retain(|&x| x != w);
+    }
+
+    /// Record that `w` received the focus.
+    pub fn touch(&mut self, w: xlib::Window) {
+        self.focus_history.retain(|&x| x != w);
+        self.focus_history.insert(0, w);
+    }
+
+    pub fn last_focused(&self) -> Option<xlib::Window> {
+        self.focus_history.first().copied()
+    }
+}